@@ -1,15 +1,459 @@
-use futures_util::{StreamExt, TryStreamExt};
+use futures_util::StreamExt;
 use lapin::{
-    options::*, types::FieldTable, BasicProperties, Connection, ConnectionProperties,
+    message::Delivery,
+    options::*,
+    types::{AMQPValue, FieldTable, ShortString},
+    BasicProperties, Channel, Connection, ConnectionProperties, ExchangeKind,
 };
 use serde_json::{json, Value};
 use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{mpsc, Semaphore};
+use tokio::time::{sleep, Duration, Instant};
 use tracing::{info, error, warn, debug};
 
+const MAX_SLEEP_MS: u64 = 30_000;
+const MAX_LONG_TASK_STEPS: u64 = 100;
+const DEFAULT_CONCURRENCY: u16 = 4;
+/// Requests that cannot be answered because they carry no `reply_to` end up here.
+const DEAD_LETTER_QUEUE: &str = "rpc_dead_letters";
+const HEARTBEAT_EXCHANGE: &str = "rpc_heartbeats";
+/// Requests with this content type, a `"jsonrpc": "2.0"` member or a JSON array body are
+/// JSON-RPC 2.0 and answered in kind.
+const JSONRPC_CONTENT_TYPE: &str = "application/json-rpc";
+const JSONRPC_PARSE_ERROR: i64 = -32700;
+const JSONRPC_INVALID_REQUEST: i64 = -32600;
+const JSONRPC_METHOD_NOT_FOUND: i64 = -32601;
+const JSONRPC_INVALID_PARAMS: i64 = -32602;
+const JSONRPC_INTERNAL_ERROR: i64 = -32603;
+/// Start of the range JSON-RPC leaves for application errors, used for `method_failed`.
+const JSONRPC_SERVER_ERROR: i64 = -32000;
+/// Scatter-gather requests reach every worker through its own queue bound here.
+const BROADCAST_EXCHANGE: &str = "rpc_broadcast";
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Error half of the reply envelope, sent as
+/// `{"success": false, "error": {"code": ..., "message": ..., "details": ...}}`.
+#[derive(Debug)]
+struct RpcFailure {
+    code: &'static str,
+    message: String,
+    details: Option<Value>,
+}
+
+impl RpcFailure {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    fn reply(&self, method: Option<&str>) -> Value {
+        json!({
+            "success": false,
+            "method": method,
+            "error": {
+                "code": self.code,
+                "message": self.message,
+                "details": self.details
+            },
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        })
+    }
+}
+
+/// This worker's instance id, sent as `app_id` on every reply.
+static WORKER_ID: OnceLock<String> = OnceLock::new();
+
+fn worker_id() -> &'static str {
+    WORKER_ID.get_or_init(|| {
+        std::env::var("RPC_WORKER_ID").unwrap_or_else(|_| {
+            let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+            let suffix = uuid::Uuid::new_v4().simple().to_string();
+            format!("rpc-{}-{}", host, &suffix[..8])
+        })
+    })
+}
+
+static STARTED_AT: OnceLock<chrono::DateTime<chrono::Utc>> = OnceLock::new();
+
+fn started_at() -> chrono::DateTime<chrono::Utc> {
+    *STARTED_AT.get_or_init(chrono::Utc::now)
+}
+
+/// Request counters reported in heartbeats.
+struct WorkerCounters {
+    processed: AtomicU64,
+    failed: AtomicU64,
+    in_flight: AtomicU64,
+}
+
+/// Kept so `status` can open its own channel for the queue depth lookup.
+static CONNECTION: OnceLock<Connection> = OnceLock::new();
+static CONCURRENCY: OnceLock<u16> = OnceLock::new();
+
+static COUNTERS: WorkerCounters = WorkerCounters {
+    processed: AtomicU64::new(0),
+    failed: AtomicU64::new(0),
+    in_flight: AtomicU64::new(0),
+};
+
+type HandlerFuture = Pin<Box<dyn Future<Output = Result<Value, String>> + Send>>;
+
+/// Lets a method send progress parts ahead of its final reply. Every reply carries an
+/// `x-seq` header counting from 0 and an `x-final` header that is only true on the last one.
+struct Progress(mpsc::UnboundedSender<Value>);
+
+impl Progress {
+    fn channel() -> (Self, mpsc::UnboundedReceiver<Value>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self(tx), rx)
+    }
+
+    fn report(&self, progress: Value) {
+        if self.0.send(progress).is_err() {
+            debug!("Progress part dropped, reply forwarder has stopped");
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ParamKind {
+    Number,
+    String,
+}
+
+impl ParamKind {
+    fn name(self) -> &'static str {
+        match self {
+            ParamKind::Number => "number",
+            ParamKind::String => "string",
+        }
+    }
+
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            ParamKind::Number => value.is_number(),
+            ParamKind::String => value.is_string(),
+        }
+    }
+}
+
+struct ParamSpec {
+    name: &'static str,
+    kind: ParamKind,
+    required: bool,
+}
+
+/// An RPC method: requests name it in `type` and carry its arguments in `params`,
+/// which are checked against `params` before the handler runs.
+struct MethodSpec {
+    name: &'static str,
+    description: &'static str,
+    params: &'static [ParamSpec],
+    handler: fn(Value, Progress) -> HandlerFuture,
+}
+
+const METHODS: &[MethodSpec] = &[
+    MethodSpec {
+        name: "echo",
+        description: "Returns the params unchanged",
+        params: &[],
+        handler: echo,
+    },
+    MethodSpec {
+        name: "add",
+        description: "Adds two numbers",
+        params: &[
+            ParamSpec { name: "a", kind: ParamKind::Number, required: true },
+            ParamSpec { name: "b", kind: ParamKind::Number, required: true },
+        ],
+        handler: add,
+    },
+    MethodSpec {
+        name: "sleep",
+        description: "Waits `ms` milliseconds (at most 30000) before replying",
+        params: &[ParamSpec { name: "ms", kind: ParamKind::Number, required: true }],
+        handler: sleep_for,
+    },
+    MethodSpec {
+        name: "long_task",
+        description: "Works through `steps` steps (at most 100) of `step_ms` milliseconds, reporting progress after each",
+        params: &[
+            ParamSpec { name: "steps", kind: ParamKind::Number, required: true },
+            ParamSpec { name: "step_ms", kind: ParamKind::Number, required: false },
+        ],
+        handler: long_task,
+    },
+    MethodSpec {
+        name: "fail",
+        description: "Always fails, with `message` as the error if given",
+        params: &[ParamSpec { name: "message", kind: ParamKind::String, required: false }],
+        handler: fail,
+    },
+    MethodSpec {
+        name: "status",
+        description: "Reports the worker's uptime, request counts, queue depth and memory use",
+        params: &[],
+        handler: status,
+    },
+    MethodSpec {
+        name: "list_methods",
+        description: "Lists the methods this worker serves",
+        params: &[],
+        handler: list_methods,
+    },
+];
+
+/// Request types from before the method registry, mapped to their method.
+const ALIASES: &[(&str, &str)] = &[("status_check", "status")];
+
+fn find_method(name: &str) -> Option<&'static MethodSpec> {
+    let name = ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, method)| method);
+    METHODS.iter().find(|m| m.name == name)
+}
+
+fn validate_params(method: &MethodSpec, params: &Value) -> Result<(), RpcFailure> {
+    if method.params.is_empty() {
+        return Ok(());
+    }
+    let Some(params) = params.as_object() else {
+        return Err(RpcFailure::new(
+            "invalid_params",
+            format!("{} expects params to be an object", method.name),
+        ));
+    };
+    for spec in method.params {
+        let details = json!({ "param": spec.name, "expected": spec.kind.name() });
+        match params.get(spec.name) {
+            Some(value) if !spec.kind.matches(value) => {
+                return Err(RpcFailure::new(
+                    "invalid_params",
+                    format!("Param '{}' must be a {}", spec.name, spec.kind.name()),
+                ).with_details(details));
+            }
+            None if spec.required => {
+                return Err(RpcFailure::new(
+                    "invalid_params",
+                    format!("Missing required param '{}'", spec.name),
+                ).with_details(details));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn echo(params: Value, _progress: Progress) -> HandlerFuture {
+    Box::pin(async move { Ok(json!({ "echo": params })) })
+}
+
+fn add(params: Value, _progress: Progress) -> HandlerFuture {
+    Box::pin(async move {
+        let a = params["a"].as_f64().unwrap_or_default();
+        let b = params["b"].as_f64().unwrap_or_default();
+        Ok(json!({ "sum": a + b }))
+    })
+}
+
+fn sleep_for(params: Value, _progress: Progress) -> HandlerFuture {
+    Box::pin(async move {
+        let ms = params["ms"]
+            .as_u64()
+            .ok_or_else(|| "Param 'ms' must be a non-negative integer".to_string())?;
+        if ms > MAX_SLEEP_MS {
+            return Err(format!("Param 'ms' must be at most {}", MAX_SLEEP_MS));
+        }
+        sleep(Duration::from_millis(ms)).await;
+        Ok(json!({ "slept_ms": ms }))
+    })
+}
+
+fn long_task(params: Value, progress: Progress) -> HandlerFuture {
+    Box::pin(async move {
+        let steps = params["steps"]
+            .as_u64()
+            .filter(|s| (1..=MAX_LONG_TASK_STEPS).contains(s))
+            .ok_or_else(|| format!("Param 'steps' must be an integer from 1 to {}", MAX_LONG_TASK_STEPS))?;
+        let step_ms = params["step_ms"].as_u64().unwrap_or(500);
+        // `step_ms` comes from the caller, so the total must not be allowed to overflow
+        if steps.checked_mul(step_ms).is_none_or(|total| total > MAX_SLEEP_MS) {
+            return Err(format!("steps * step_ms must be at most {}", MAX_SLEEP_MS));
+        }
+        let started = Instant::now();
+        for step in 1..=steps {
+            sleep(Duration::from_millis(step_ms)).await;
+            if step < steps {
+                progress.report(json!({
+                    "step": step,
+                    "steps": steps,
+                    "percent": step * 100 / steps
+                }));
+            }
+        }
+        Ok(json!({ "steps": steps, "elapsed_ms": started.elapsed().as_millis() as u64 }))
+    })
+}
+
+fn fail(params: Value, _progress: Progress) -> HandlerFuture {
+    Box::pin(async move {
+        Err(params["message"]
+            .as_str()
+            .unwrap_or("Requested failure")
+            .to_string())
+    })
+}
+
+fn status(_params: Value, _progress: Progress) -> HandlerFuture {
+    Box::pin(async move {
+        let started_at = started_at();
+        let in_flight = COUNTERS.in_flight.load(Ordering::SeqCst);
+        let concurrency = CONCURRENCY.get().copied().unwrap_or(DEFAULT_CONCURRENCY);
+        let version = env!("CARGO_PKG_VERSION");
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+        let depth = request_queue_depth().await;
+        let queue = match &depth {
+            Ok((messages, consumers)) => json!({
+                "name": "rpc_requests",
+                "messages": messages,
+                "consumers": consumers
+            }),
+            Err(e) => json!({ "name": "rpc_requests", "error": e }),
+        };
+        // This status request occupies one of the slots itself. Once it is answered that
+        // slot frees up, so the worker is only saturated when every other slot is busy and
+        // requests are already waiting for one.
+        let busy = in_flight.saturating_sub(1);
+        let waiting = depth.as_ref().map_or(0, |(messages, _)| *messages);
+        let saturated = busy + 1 >= concurrency as u64 && waiting > 0;
+
+        Ok(json!({
+            "status": if saturated { "Saturated" } else { "Operational" },
+            "server_info": format!("rpc_worker v{} ({} on {})", version, worker_id(), host),
+            "worker_id": worker_id(),
+            "version": version,
+            "host": host,
+            "pid": std::process::id(),
+            "started_at": started_at.to_rfc3339(),
+            "uptime_secs": (chrono::Utc::now() - started_at).num_seconds(),
+            "requests": {
+                "processed": COUNTERS.processed.load(Ordering::SeqCst),
+                "failed": COUNTERS.failed.load(Ordering::SeqCst),
+                "in_flight": busy,
+                "concurrency": concurrency
+            },
+            "queue": queue,
+            "memory_rss_bytes": resident_memory_bytes(),
+            "processed_at": chrono::Utc::now().to_rfc3339()
+        }))
+    })
+}
+
+/// Messages ready in `rpc_requests` and its consumer count, from a passive declare on a
+/// channel of its own since a failed passive declare closes the channel.
+async fn request_queue_depth() -> Result<(u32, u32), String> {
+    let connection = CONNECTION.get().ok_or("Not connected to RabbitMQ")?;
+    let channel = connection.create_channel().await.map_err(|e| e.to_string())?;
+    let declared = channel
+        .queue_declare(
+            "rpc_requests",
+            QueueDeclareOptions {
+                passive: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await;
+    if let Err(e) = channel.close(200, "OK").await {
+        debug!("Failed to close status channel: {}", e);
+    }
+    let queue = declared.map_err(|e| {
+        warn!("Failed to inspect rpc_requests queue: {}", e);
+        e.to_string()
+    })?;
+    Ok((queue.message_count(), queue.consumer_count()))
+}
+
+/// Resident set size from `/proc/self/status`; `None` where that is not available.
+fn resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+fn list_methods(_params: Value, _progress: Progress) -> HandlerFuture {
+    Box::pin(async move {
+        let methods: Vec<Value> = METHODS
+            .iter()
+            .map(|m| json!({
+                "name": m.name,
+                "description": m.description,
+                "params": m.params.iter().map(|p| json!({
+                    "name": p.name,
+                    "type": p.kind.name(),
+                    "required": p.required
+                })).collect::<Vec<_>>()
+            }))
+            .collect();
+        Ok(json!({ "methods": methods }))
+    })
+}
+
+/// Runs the method named by the request's `type` and builds the reply.
+async fn handle_request(request: &Value, progress: Progress) -> Value {
+    let request_type = request.get("type").and_then(|t| t.as_str()).unwrap_or("unknown");
+    info!("Processing RPC request type: {}", request_type);
+
+    let Some(method) = find_method(request_type) else {
+        warn!("Unknown RPC request type: {}", request_type);
+        let available: Vec<&str> = METHODS.iter().map(|m| m.name).collect();
+        return RpcFailure::new("unknown_method", format!("Unknown request type '{}'", request_type))
+            .with_details(json!({ "available": available }))
+            .reply(Some(request_type));
+    };
+
+    let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+    if let Err(failure) = validate_params(method, &params) {
+        warn!("Invalid params for {}: {}", method.name, failure.message);
+        return failure.reply(Some(method.name));
+    }
+
+    match (method.handler)(params, progress).await {
+        Ok(result) => json!({
+            "success": true,
+            "method": method.name,
+            "result": result,
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        }),
+        Err(e) => RpcFailure::new("method_failed", e).reply(Some(method.name)),
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
     tracing_subscriber::fmt::init();
-    info!("Starting RPC Worker");
+    started_at();
+    info!("Starting RPC Worker {}", worker_id());
 
     info!("Connecting to RabbitMQ");
     let connection = Connection::connect(
@@ -21,6 +465,7 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         error!("Failed to connect to RabbitMQ: {}", e);
         e
     })?;
+    let connection = CONNECTION.get_or_init(|| connection);
 
     info!("Creating RabbitMQ channel");
     let channel = connection.create_channel().await
@@ -42,11 +487,53 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
             e
         })?;
 
+    info!("Declaring RPC dead-letter queue");
+    channel
+        .queue_declare(
+            DEAD_LETTER_QUEUE,
+            QueueDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to declare {} queue: {}", DEAD_LETTER_QUEUE, e);
+            e
+        })?;
+
+    info!("Declaring exchange: {} (fanout)", HEARTBEAT_EXCHANGE);
+    channel
+        .exchange_declare(
+            HEARTBEAT_EXCHANGE,
+            ExchangeKind::Fanout,
+            ExchangeDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to declare {} exchange: {}", HEARTBEAT_EXCHANGE, e);
+            e
+        })?;
+
+    let concurrency = std::env::var("RPC_WORKER_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse::<u16>().ok())
+        .filter(|c| *c > 0)
+        .unwrap_or(DEFAULT_CONCURRENCY);
+    CONCURRENCY.get_or_init(|| concurrency);
+    // The broker never hands over more requests than can be worked on at once
+    channel
+        .basic_qos(concurrency, BasicQosOptions::default())
+        .await
+        .map_err(|e| {
+            error!("Failed to set prefetch: {}", e);
+            e
+        })?;
+
     info!("Creating consumer for RPC requests");
     let consumer = channel
         .basic_consume(
             "rpc_requests",
-            "rpc_worker",
+            worker_id(),
             BasicConsumeOptions::default(),
             FieldTable::default(),
         )
@@ -56,101 +543,95 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
             e
         })?;
 
-    info!("RPC Worker ready - listening for requests on 'rpc_requests' queue");
+    info!("Declaring exchange: {} (fanout)", BROADCAST_EXCHANGE);
+    channel
+        .exchange_declare(
+            BROADCAST_EXCHANGE,
+            ExchangeKind::Fanout,
+            ExchangeDeclareOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to declare {} exchange: {}", BROADCAST_EXCHANGE, e);
+            e
+        })?;
 
-    let mut stream = consumer.into_stream();
+    let broadcast_queue = channel
+        .queue_declare(
+            "",
+            QueueDeclareOptions {
+                exclusive: true,
+                auto_delete: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to declare broadcast queue: {}", e);
+            e
+        })?;
+    channel
+        .queue_bind(
+            broadcast_queue.name().as_str(),
+            BROADCAST_EXCHANGE,
+            "",
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to bind broadcast queue to {}: {}", BROADCAST_EXCHANGE, e);
+            e
+        })?;
+
+    info!("Creating consumer for broadcast RPC requests on {}", broadcast_queue.name().as_str());
+    let broadcast_consumer = channel
+        .basic_consume(
+            broadcast_queue.name().as_str(),
+            &format!("{}-broadcast", worker_id()),
+            BasicConsumeOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to create broadcast consumer: {}", e);
+            e
+        })?;
+
+    info!(
+        "RPC Worker ready - listening for requests on 'rpc_requests' queue and the {} exchange (concurrency {})",
+        BROADCAST_EXCHANGE, concurrency
+    );
+
+    publish_heartbeat(&channel, "joined", concurrency).await;
+
+    let heartbeat_channel = channel.clone();
+    let heartbeat_task = tokio::spawn(async move {
+        loop {
+            sleep(HEARTBEAT_INTERVAL).await;
+            publish_heartbeat(&heartbeat_channel, "heartbeat", concurrency).await;
+        }
+    });
+
+    let permits = Arc::new(Semaphore::new(concurrency as usize));
+    // Both consumers share the permits, so broadcast requests count towards the concurrency
+    let mut stream = futures_util::stream::select(consumer, broadcast_consumer);
     debug!("Starting message processing loop");
-    
+
     while let Some(delivery_result) = stream.next().await {
         match delivery_result {
             Ok(delivery) => {
                 debug!("Received RPC message");
-                
-                if let Some(reply_to) = delivery.properties.reply_to() {
-                    if let Some(correlation_id) = delivery.properties.correlation_id() {
-                        info!("Processing RPC request with correlation_id: {}", correlation_id.as_str());
-                        
-                        let request: Value = match serde_json::from_slice(&delivery.data) {
-                            Ok(req) => {
-                                debug!("Successfully parsed RPC request: {}", req);
-                                req
-                            },
-                            Err(e) => {
-                                error!("Failed to parse RPC request: {}", e);
-                                // Still acknowledge the message to remove it from queue
-                                if let Err(ack_err) = delivery.ack(BasicAckOptions::default()).await {
-                                    error!("Failed to ack malformed message: {}", ack_err);
-                                }
-                                continue;
-                            }
-                        };
-
-                        let request_type = request.get("type").and_then(|t| t.as_str()).unwrap_or("unknown");
-                        info!("Processing RPC request type: {}", request_type);
-
-                        let response = match request_type {
-                            "status_check" => {
-                                info!("Handling status check request");
-                                json!({
-                                    "success": true,
-                                    "timestamp": chrono::Utc::now().to_rfc3339(),
-                                    "status": "All systems operational",
-                                    "server_info": "RabbitMQ Demo Server v1.0",
-                                    "uptime": "Running smoothly",
-                                    "queue_status": "Active",
-                                    "processed_at": chrono::Utc::now().to_rfc3339()
-                                })
-                            }
-                            _ => {
-                                warn!("Unknown RPC request type: {}", request_type);
-                                json!({
-                                    "success": false,
-                                    "error": "Unknown request type",
-                                    "timestamp": chrono::Utc::now().to_rfc3339()
-                                })
-                            }
-                        };
-
-                        debug!("Generated RPC response: {}", response);
-
-                        let properties = BasicProperties::default()
-                            .with_correlation_id(correlation_id.clone());
-
-                        match channel
-                            .basic_publish(
-                                "",
-                                reply_to.as_str(),
-                                BasicPublishOptions::default(),
-                                serde_json::to_string(&response)
-                                    .map_err(|e| {
-                                        error!("Failed to serialize RPC response: {}", e);
-                                        e
-                                    })?
-                                    .as_bytes(),
-                                properties,
-                            )
-                            .await
-                        {
-                            Ok(_) => {
-                                info!("Successfully sent RPC response to {} with correlation_id: {}", 
-                                     reply_to.as_str(), correlation_id.as_str());
-                            }
-                            Err(e) => {
-                                error!("Failed to send RPC response to {}: {}", reply_to.as_str(), e);
-                            }
-                        }
-                    } else {
-                        warn!("Received RPC request without correlation_id");
-                    }
-                } else {
-                    warn!("Received RPC request without reply_to address");
-                }
-
-                if let Err(e) = delivery.ack(BasicAckOptions::default()).await {
-                    error!("Failed to acknowledge RPC message: {}", e);
-                } else {
-                    debug!("RPC message acknowledged successfully");
-                }
+                let permit = permits.clone().acquire_owned().await?;
+                let channel = channel.clone();
+                COUNTERS.in_flight.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    process_delivery(channel, delivery).await;
+                    COUNTERS.in_flight.fetch_sub(1, Ordering::SeqCst);
+                    drop(permit);
+                });
             }
             Err(e) => {
                 error!("Error receiving RPC message: {}", e);
@@ -158,8 +639,401 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
             }
         }
     }
-    
+
     warn!("RPC Worker message processing loop ended");
+    // Let in-flight requests finish replying before the process exits
+    let _ = permits.acquire_many(concurrency as u32).await;
+    heartbeat_task.abort();
+    publish_heartbeat(&channel, "leaving", concurrency).await;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Handles one request: runs the method, publishes the reply and only then acks, so a
+/// request is never acknowledged before its caller has been answered.
+async fn process_delivery(channel: Channel, delivery: Delivery) {
+    let received_at = Instant::now();
+
+    if is_jsonrpc(&delivery) {
+        process_jsonrpc(&channel, &delivery).await;
+        ack(&delivery).await;
+        info!("JSON-RPC delivery took {}ms including reply and ack", received_at.elapsed().as_millis());
+        return;
+    }
+
+    let Some(reply_to) = delivery.properties.reply_to().clone() else {
+        warn!("Received RPC request without reply_to address, dead-lettering it");
+        dead_letter(&channel, &delivery, "missing_reply_to").await;
+        ack(&delivery).await;
+        return;
+    };
+    let correlation_id = delivery.properties.correlation_id().clone();
+
+    let (progress, parts) = Progress::channel();
+    let forwarder = tokio::spawn(forward_progress(
+        channel.clone(),
+        reply_to.clone(),
+        correlation_id.clone(),
+        parts,
+    ));
+    let response = build_response(&delivery.data, correlation_id.as_ref(), progress).await;
+    // The progress sender is gone once the method returns, so this waits for the last part
+    let seq = forwarder.await.unwrap_or_else(|e| {
+        error!("Progress forwarder failed: {}", e);
+        0
+    });
+    let handled_ms = received_at.elapsed().as_millis();
+    debug!("Generated RPC response: {}", response);
+
+    send_reply(&channel, &reply_to, correlation_id.as_ref(), &response, seq, true).await;
+    ack(&delivery).await;
+    COUNTERS.processed.fetch_add(1, Ordering::SeqCst);
+    if response["success"].as_bool() != Some(true) {
+        COUNTERS.failed.fetch_add(1, Ordering::SeqCst);
+    }
+    info!(
+        "RPC request {} ({}) took {}ms to handle, {}ms including reply and ack",
+        correlation_id.as_ref().map_or("-", |id| id.as_str()),
+        response["method"].as_str().unwrap_or("unknown"),
+        handled_ms,
+        received_at.elapsed().as_millis()
+    );
+}
+
+async fn build_response(data: &[u8], correlation_id: Option<&ShortString>, progress: Progress) -> Value {
+    match (serde_json::from_slice::<Value>(data), correlation_id) {
+        (Err(e), _) => {
+            error!("Failed to parse RPC request: {}", e);
+            RpcFailure::new("parse_error", "Request body is not valid JSON")
+                .with_details(json!({ "error": e.to_string() }))
+                .reply(None)
+        }
+        (Ok(_), None) => {
+            warn!("Received RPC request without correlation_id");
+            RpcFailure::new("missing_correlation_id", "Request has no correlation_id to answer with")
+                .reply(None)
+        }
+        (Ok(request), Some(correlation_id)) => {
+            info!("Processing RPC request with correlation_id: {}", correlation_id.as_str());
+            debug!("Successfully parsed RPC request: {}", request);
+            handle_request(&request, progress).await
+        }
+    }
+}
+
+/// Sends each progress part as a non-final reply and returns the sequence number left
+/// for the final reply.
+async fn forward_progress(
+    channel: Channel,
+    reply_to: ShortString,
+    correlation_id: Option<ShortString>,
+    mut parts: mpsc::UnboundedReceiver<Value>,
+) -> u32 {
+    let mut seq = 0;
+    while let Some(progress) = parts.recv().await {
+        let part = json!({
+            "success": true,
+            "progress": progress,
+            "timestamp": chrono::Utc::now().to_rfc3339()
+        });
+        send_reply(&channel, &reply_to, correlation_id.as_ref(), &part, seq, false).await;
+        seq += 1;
+    }
+    seq
+}
+
+fn is_jsonrpc(delivery: &Delivery) -> bool {
+    if delivery
+        .properties
+        .content_type()
+        .as_ref()
+        .is_some_and(|t| t.as_str() == JSONRPC_CONTENT_TYPE)
+    {
+        return true;
+    }
+    match serde_json::from_slice::<Value>(&delivery.data) {
+        Ok(Value::Array(_)) => true,
+        Ok(body) => body.get("jsonrpc").is_some(),
+        Err(_) => false,
+    }
+}
+
+fn jsonrpc_error(id: Value, code: i64, message: &str, data: Option<Value>) -> Value {
+    let mut error = json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "error": error, "id": id })
+}
+
+/// Answers a JSON-RPC request or batch. Notifications get no reply, so a delivery made up
+/// only of notifications needs no `reply_to` and is never dead-lettered.
+async fn process_jsonrpc(channel: &Channel, delivery: &Delivery) {
+    let reply = match serde_json::from_slice::<Value>(&delivery.data) {
+        Err(e) => {
+            error!("Failed to parse JSON-RPC request: {}", e);
+            Some(jsonrpc_error(
+                Value::Null,
+                JSONRPC_PARSE_ERROR,
+                "Parse error",
+                Some(json!({ "error": e.to_string() })),
+            ))
+        }
+        Ok(Value::Array(batch)) if batch.is_empty() => Some(jsonrpc_error(
+            Value::Null,
+            JSONRPC_INVALID_REQUEST,
+            "Invalid Request",
+            Some(json!({ "error": "Empty batch" })),
+        )),
+        Ok(Value::Array(batch)) => {
+            info!("Processing JSON-RPC batch of {} requests", batch.len());
+            // The whole batch holds a single concurrency permit, so its calls run one after
+            // another instead of taking more than one slot's worth of work
+            let mut replies = Vec::new();
+            for request in &batch {
+                replies.extend(handle_jsonrpc(request).await);
+            }
+            (!replies.is_empty()).then_some(Value::Array(replies))
+        }
+        Ok(request) => handle_jsonrpc(&request).await,
+    };
+
+    COUNTERS.processed.fetch_add(1, Ordering::SeqCst);
+    let Some(reply) = reply else {
+        debug!("JSON-RPC notification handled, nothing to reply");
+        return;
+    };
+    let failed = match &reply {
+        Value::Array(replies) => replies.iter().any(|r| r.get("error").is_some()),
+        reply => reply.get("error").is_some(),
+    };
+    if failed {
+        COUNTERS.failed.fetch_add(1, Ordering::SeqCst);
+    }
+
+    match delivery.properties.reply_to() {
+        Some(reply_to) => {
+            let correlation_id = delivery.properties.correlation_id().as_ref();
+            send_reply(channel, reply_to, correlation_id, &reply, 0, true).await;
+        }
+        None => {
+            warn!("Received JSON-RPC request without reply_to address, dead-lettering it");
+            dead_letter(channel, delivery, "missing_reply_to").await;
+        }
+    }
+}
+
+/// Runs one JSON-RPC request through the method registry. Returns `None` for
+/// notifications, i.e. valid requests without an `id`.
+async fn handle_jsonrpc(request: &Value) -> Option<Value> {
+    let id = request.get("id").cloned();
+    let reply_id = id.clone().unwrap_or(Value::Null);
+    let method = match (request.get("jsonrpc").and_then(Value::as_str), request.get("method").and_then(Value::as_str)) {
+        (Some("2.0"), Some(method)) => method,
+        _ => {
+            warn!("Invalid JSON-RPC request: {}", request);
+            return Some(jsonrpc_error(
+                reply_id,
+                JSONRPC_INVALID_REQUEST,
+                "Invalid Request",
+                Some(json!({ "error": "Expected \"jsonrpc\": \"2.0\" and a string \"method\"" })),
+            ));
+        }
+    };
+
+    let params = match request.get("params") {
+        None => json!({}),
+        Some(Value::Object(params)) => Value::Object(params.clone()),
+        // Positional params are matched to the method's declared params in order
+        Some(Value::Array(args)) => match find_method(method) {
+            Some(spec) if !spec.params.is_empty() => Value::Object(
+                spec.params
+                    .iter()
+                    .zip(args)
+                    .map(|(p, arg)| (p.name.to_string(), arg.clone()))
+                    .collect(),
+            ),
+            _ => Value::Array(args.clone()),
+        },
+        Some(_) => {
+            return Some(jsonrpc_error(
+                reply_id,
+                JSONRPC_INVALID_REQUEST,
+                "Invalid Request",
+                Some(json!({ "error": "params must be an array or an object" })),
+            ));
+        }
+    };
+
+    // JSON-RPC has no progress parts, so anything a method reports is dropped
+    let (progress, _) = Progress::channel();
+    let response = handle_request(&json!({ "type": method, "params": params }), progress).await;
+    let id = id?;
+
+    if response["success"].as_bool() == Some(true) {
+        return Some(json!({ "jsonrpc": "2.0", "result": response["result"], "id": id }));
+    }
+    let error = &response["error"];
+    let code = match error["code"].as_str() {
+        Some("unknown_method") => JSONRPC_METHOD_NOT_FOUND,
+        Some("invalid_params") => JSONRPC_INVALID_PARAMS,
+        Some("method_failed") => JSONRPC_SERVER_ERROR,
+        _ => JSONRPC_INTERNAL_ERROR,
+    };
+    Some(jsonrpc_error(
+        id,
+        code,
+        error["message"].as_str().unwrap_or("Internal error"),
+        Some(json!({ "code": error["code"], "details": error["details"] })),
+    ))
+}
+
+/// Announces this worker on the `rpc_heartbeats` exchange so the server can list live
+/// RPC workers; `event_type` is `joined`, `heartbeat` or `leaving`.
+async fn publish_heartbeat(channel: &Channel, event_type: &str, concurrency: u16) {
+    let started_at = started_at();
+    let event = json!({
+        "type": event_type,
+        "worker_id": worker_id(),
+        "host": std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string()),
+        "pid": std::process::id(),
+        "started_at": started_at.to_rfc3339(),
+        "uptime_secs": (chrono::Utc::now() - started_at).num_seconds(),
+        "processed": COUNTERS.processed.load(Ordering::SeqCst),
+        "failed": COUNTERS.failed.load(Ordering::SeqCst),
+        "in_flight": COUNTERS.in_flight.load(Ordering::SeqCst),
+        "concurrency": concurrency,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    });
+
+    match channel
+        .basic_publish(
+            HEARTBEAT_EXCHANGE,
+            "",
+            BasicPublishOptions::default(),
+            event.to_string().as_bytes(),
+            BasicProperties::default(),
+        )
+        .await
+    {
+        Ok(_) => debug!("Published {} event", event_type),
+        Err(e) => error!("Failed to publish {} event: {}", event_type, e),
+    }
+}
+
+async fn send_reply(
+    channel: &Channel,
+    reply_to: &ShortString,
+    correlation_id: Option<&ShortString>,
+    response: &Value,
+    seq: u32,
+    is_final: bool,
+) {
+    let mut headers = FieldTable::default();
+    headers.insert("x-seq".into(), AMQPValue::LongUInt(seq));
+    headers.insert("x-final".into(), AMQPValue::Boolean(is_final));
+    let mut properties = BasicProperties::default()
+        .with_app_id(worker_id().into())
+        .with_headers(headers);
+    if let Some(correlation_id) = correlation_id {
+        properties = properties.with_correlation_id(correlation_id.clone());
+    }
+
+    let published = match channel
+        .basic_publish(
+            "",
+            reply_to.as_str(),
+            BasicPublishOptions::default(),
+            response.to_string().as_bytes(),
+            properties,
+        )
+        .await
+    {
+        Ok(confirm) => confirm.await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    match published {
+        Ok(()) => {
+            info!("Successfully sent RPC {} {} to {} with correlation_id: {:?}",
+                 if is_final { "response" } else { "progress part" }, seq,
+                 reply_to.as_str(), correlation_id.map(|id| id.as_str()));
+        }
+        Err(e) => {
+            error!("Failed to send RPC response to {}: {}", reply_to.as_str(), e);
+        }
+    }
+}
+
+/// Copies a request nobody can be answered about to the dead-letter queue, keeping its
+/// body and properties and recording why in `x-dead-letter-reason`.
+async fn dead_letter(channel: &Channel, delivery: &Delivery, reason: &str) {
+    let mut headers = delivery.properties.headers().clone().unwrap_or_default();
+    headers.insert("x-dead-letter-reason".into(), AMQPValue::LongString(reason.into()));
+    if delivery.exchange.as_str().is_empty() {
+        headers.insert("x-original-queue".into(), AMQPValue::LongString("rpc_requests".into()));
+    } else {
+        headers.insert(
+            "x-original-exchange".into(),
+            AMQPValue::LongString(delivery.exchange.as_str().into()),
+        );
+    }
+    headers.insert("x-dead-lettered-by".into(), AMQPValue::LongString(worker_id().into()));
+    headers.insert(
+        "x-dead-lettered-at".into(),
+        AMQPValue::LongString(chrono::Utc::now().to_rfc3339().into()),
+    );
+    let properties = delivery.properties.clone().with_headers(headers);
+
+    let published = match channel
+        .basic_publish(
+            "",
+            DEAD_LETTER_QUEUE,
+            BasicPublishOptions::default(),
+            &delivery.data,
+            properties,
+        )
+        .await
+    {
+        Ok(confirm) => confirm.await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    match published {
+        Ok(()) => info!("Dead-lettered RPC request ({})", reason),
+        Err(e) => error!("Failed to dead-letter RPC request ({}): {}", reason, e),
+    }
+}
+
+async fn ack(delivery: &Delivery) {
+    if let Err(e) = delivery.ack(BasicAckOptions::default()).await {
+        error!("Failed to acknowledge RPC message: {}", e);
+    } else {
+        debug!("RPC message acknowledged successfully");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn long_task_rejects_overflowing_durations() {
+        for (steps, step_ms) in [(2, (1u64 << 63) + 1), (MAX_LONG_TASK_STEPS, u64::MAX)] {
+            let (progress, _parts) = Progress::channel();
+            let result = long_task(json!({ "steps": steps, "step_ms": step_ms }), progress).await;
+            assert_eq!(result, Err(format!("steps * step_ms must be at most {}", MAX_SLEEP_MS)));
+        }
+    }
+
+    #[tokio::test]
+    async fn long_task_reports_progress_within_the_limit() {
+        let (progress, mut parts) = Progress::channel();
+        let result = long_task(json!({ "steps": 3, "step_ms": 1 }), progress).await.unwrap();
+        assert_eq!(result["steps"], 3);
+
+        let mut steps = Vec::new();
+        while let Ok(part) = parts.try_recv() {
+            steps.push(part["step"].as_u64().unwrap());
+        }
+        assert_eq!(steps, vec![1, 2]);
+    }
+}
@@ -0,0 +1,421 @@
+use std::collections::{HashMap, VecDeque};
+use serde::Serialize;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn, debug};
+
+const DEFAULT_CLICK_RATE: f64 = 12.0;
+const DEFAULT_CLICK_BURST: f64 = 20.0;
+const DEFAULT_DISQUALIFY_STRIKES: u32 = 3;
+/// Number of recent click attempts the cadence check looks at.
+const CADENCE_WINDOW: usize = 20;
+/// Sustained averages faster than this are beyond what a person can click.
+const MIN_HUMAN_INTERVAL_MS: f64 = 50.0;
+/// Human clicking jitters; intervals this regular (stddev / mean) come from a script.
+const MIN_HUMAN_VARIATION: f64 = 0.05;
+/// Every this many rate-limited attempts count as one strike.
+const RATE_LIMITED_PER_STRIKE: u32 = 50;
+/// Minimum gap between two `rate_limited` reports for the same player or address.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+/// An address may click this many times as fast as a single player, so players behind
+/// one address do not throttle each other while rotating names still runs dry.
+const ADDRESS_SHARE: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    RateLimited,
+    InhumanCadence,
+    Disqualified,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClickRejection {
+    pub reason: RejectReason,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+    pub strikes: u32,
+    pub flagged: bool,
+    pub disqualified: bool,
+    /// Whether this rejection should be reported to WebSocket clients; repeated
+    /// rate-limit rejections are only reported once per second.
+    #[serde(skip)]
+    pub report: bool,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+    last_reported: Option<Instant>,
+}
+
+impl TokenBucket {
+    fn new(burst: f64, now: Instant) -> Self {
+        Self {
+            tokens: burst,
+            refilled_at: now,
+            last_reported: None,
+        }
+    }
+
+    /// Refills for the time since the last click; returns how long until a token is
+    /// available if the bucket is empty.
+    fn refill(&mut self, rate: f64, burst: f64, now: Instant) -> Option<u64> {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(burst);
+        self.refilled_at = now;
+        (self.tokens < 1.0).then(|| ((1.0 - self.tokens) / rate * 1000.0).ceil() as u64)
+    }
+
+    /// Whether a rate-limit rejection should be reported, at most once per interval.
+    fn report(&mut self, now: Instant) -> bool {
+        let report = self
+            .last_reported
+            .is_none_or(|last| now.duration_since(last) >= REPORT_INTERVAL);
+        if report {
+            self.last_reported = Some(now);
+        }
+        report
+    }
+}
+
+#[derive(Debug)]
+struct PlayerActivity {
+    bucket: TokenBucket,
+    attempts: VecDeque<Instant>,
+    rate_limited: u32,
+    strikes: u32,
+    disqualified: bool,
+}
+
+impl PlayerActivity {
+    fn new(burst: f64, now: Instant) -> Self {
+        Self {
+            bucket: TokenBucket::new(burst, now),
+            attempts: VecDeque::with_capacity(CADENCE_WINDOW),
+            rate_limited: 0,
+            strikes: 0,
+            disqualified: false,
+        }
+    }
+}
+
+/// Per room and player token buckets plus a cadence check over recent click attempts.
+/// Strikes accumulate from inhuman cadence and sustained rate limiting; one strike
+/// flags a player and `disqualify_strikes` disqualifies them for the rest of the round.
+/// Each client address also gets a looser bucket shared by every player behind it, so
+/// switching names on every click does not buy unlimited clicks.
+#[derive(Debug)]
+pub struct ClickGuard {
+    rate: f64,
+    burst: f64,
+    disqualify_strikes: u32,
+    players: HashMap<(String, String), PlayerActivity>,
+    addresses: HashMap<(String, String), TokenBucket>,
+}
+
+impl ClickGuard {
+    pub fn new(rate: f64, burst: f64, disqualify_strikes: u32) -> Self {
+        info!(
+            "Limiting game clicks to {}/s (burst {}) per player, disqualifying after {} strikes",
+            rate, burst, disqualify_strikes
+        );
+        Self {
+            rate,
+            burst,
+            disqualify_strikes,
+            players: HashMap::new(),
+            addresses: HashMap::new(),
+        }
+    }
+
+    /// Uses `GAME_CLICK_RATE`, `GAME_CLICK_BURST` and `GAME_DISQUALIFY_STRIKES`.
+    pub fn from_env() -> Self {
+        let env = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<f64>().ok());
+        Self::new(
+            env("GAME_CLICK_RATE").filter(|r| *r > 0.0).unwrap_or(DEFAULT_CLICK_RATE),
+            env("GAME_CLICK_BURST").filter(|b| *b >= 1.0).unwrap_or(DEFAULT_CLICK_BURST),
+            env("GAME_DISQUALIFY_STRIKES")
+                .filter(|s| *s >= 1.0)
+                .map(|s| s as u32)
+                .unwrap_or(DEFAULT_DISQUALIFY_STRIKES),
+        )
+    }
+
+    /// Forgets every player's and address's history in a room, e.g. when a new round starts.
+    pub fn reset_room(&mut self, room: &str) {
+        self.players.retain(|(r, _), _| r != room);
+        self.addresses.retain(|(r, _), _| r != room);
+    }
+
+    pub fn check(&mut self, room: &str, player: &str, address: &str) -> Result<(), ClickRejection> {
+        self.check_at(room, player, address, Instant::now())
+    }
+
+    fn check_at(&mut self, room: &str, player: &str, address: &str, now: Instant) -> Result<(), ClickRejection> {
+        let (rate, burst, disqualify_strikes) = (self.rate, self.burst, self.disqualify_strikes);
+        let activity = self
+            .players
+            .entry((room.to_string(), player.to_string()))
+            .or_insert_with(|| PlayerActivity::new(burst, now));
+
+        if activity.disqualified {
+            return Err(rejection(activity, RejectReason::Disqualified, None, false, disqualify_strikes));
+        }
+
+        let shared = self
+            .addresses
+            .entry((room.to_string(), address.to_string()))
+            .or_insert_with(|| TokenBucket::new(burst * ADDRESS_SHARE, now));
+        if let Some(retry_after_ms) = shared.refill(rate * ADDRESS_SHARE, burst * ADDRESS_SHARE, now) {
+            debug!("Rate limited click from {} in room {}: address {} is out of clicks", player, room, address);
+            let report = shared.report(now);
+            return Err(rejection(activity, RejectReason::RateLimited, Some(retry_after_ms), report, disqualify_strikes));
+        }
+
+        if activity.attempts.len() == CADENCE_WINDOW {
+            activity.attempts.pop_front();
+        }
+        activity.attempts.push_back(now);
+        if let Some((mean, variation)) = cadence(&activity.attempts) {
+            if mean < MIN_HUMAN_INTERVAL_MS || variation < MIN_HUMAN_VARIATION {
+                warn!(
+                    "Inhuman click cadence from {} in room {}: mean {:.1}ms, variation {:.3}",
+                    player, room, mean, variation
+                );
+                // Start a fresh window so the same clicks are not counted twice
+                activity.attempts.clear();
+                strike(activity, disqualify_strikes);
+                return Err(rejection(activity, RejectReason::InhumanCadence, None, true, disqualify_strikes));
+            }
+        }
+
+        if let Some(retry_after_ms) = activity.bucket.refill(rate, burst, now) {
+            activity.rate_limited += 1;
+            if activity.rate_limited.is_multiple_of(RATE_LIMITED_PER_STRIKE) {
+                strike(activity, disqualify_strikes);
+            }
+            let report = activity.bucket.report(now);
+            debug!("Rate limited click from {} in room {}", player, room);
+            return Err(rejection(activity, RejectReason::RateLimited, Some(retry_after_ms), report, disqualify_strikes));
+        }
+        activity.bucket.tokens -= 1.0;
+        shared.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+fn strike(activity: &mut PlayerActivity, disqualify_strikes: u32) {
+    activity.strikes += 1;
+    if activity.strikes >= disqualify_strikes {
+        activity.disqualified = true;
+    }
+}
+
+fn rejection(
+    activity: &PlayerActivity,
+    reason: RejectReason,
+    retry_after_ms: Option<u64>,
+    report: bool,
+    disqualify_strikes: u32,
+) -> ClickRejection {
+    ClickRejection {
+        reason,
+        retry_after_ms,
+        strikes: activity.strikes,
+        flagged: activity.strikes > 0,
+        disqualified: activity.strikes >= disqualify_strikes,
+        report,
+    }
+}
+
+/// Mean interval in milliseconds and its coefficient of variation, once the window is full.
+fn cadence(attempts: &VecDeque<Instant>) -> Option<(f64, f64)> {
+    if attempts.len() < CADENCE_WINDOW {
+        return None;
+    }
+    let intervals: Vec<f64> = attempts
+        .iter()
+        .zip(attempts.iter().skip(1))
+        .map(|(a, b)| b.duration_since(*a).as_secs_f64() * 1000.0)
+        .collect();
+    let mean = intervals.iter().sum::<f64>() / intervals.len() as f64;
+    if mean <= 0.0 {
+        return Some((0.0, 0.0));
+    }
+    let variance = intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / intervals.len() as f64;
+    Some((mean, variance.sqrt() / mean))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// Clicks at irregular, human-looking intervals averaging well above the cadence floor.
+    fn human_gap(i: u64) -> Duration {
+        ms(150 + (i * 37) % 90)
+    }
+
+    #[test]
+    fn burst_is_allowed_then_rate_limited() {
+        let mut guard = ClickGuard::new(1.0, 3.0, 3);
+        let start = Instant::now();
+        for i in 0..3 {
+            assert!(guard.check_at("room", "alice", "10.0.0.1", start + ms(i * 100 + i * i)).is_ok());
+        }
+        let rejection = guard.check_at("room", "alice", "10.0.0.1", start + ms(310)).unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::RateLimited);
+        assert!(rejection.report);
+        assert!(rejection.retry_after_ms.is_some_and(|ms| ms > 0 && ms <= 1000));
+        assert!(!rejection.flagged);
+
+        // Reported at most once per second
+        let again = guard.check_at("room", "alice", "10.0.0.1", start + ms(420)).unwrap_err();
+        assert!(!again.report);
+    }
+
+    #[test]
+    fn tokens_refill_over_time() {
+        let mut guard = ClickGuard::new(2.0, 2.0, 3);
+        let start = Instant::now();
+        assert!(guard.check_at("room", "alice", "10.0.0.1", start).is_ok());
+        assert!(guard.check_at("room", "alice", "10.0.0.1", start + ms(10)).is_ok());
+        assert!(guard.check_at("room", "alice", "10.0.0.1", start + ms(20)).is_err());
+        // Half a second at 2/s buys one click back
+        assert!(guard.check_at("room", "alice", "10.0.0.1", start + ms(520)).is_ok());
+        assert!(guard.check_at("room", "alice", "10.0.0.1", start + ms(530)).is_err());
+        // The bucket never holds more than the burst
+        let later = start + Duration::from_secs(60);
+        assert!(guard.check_at("room", "alice", "10.0.0.1", later).is_ok());
+        assert!(guard.check_at("room", "alice", "10.0.0.1", later + ms(1)).is_ok());
+        assert!(guard.check_at("room", "alice", "10.0.0.1", later + ms(2)).is_err());
+    }
+
+    #[test]
+    fn players_and_rooms_have_separate_buckets() {
+        let mut guard = ClickGuard::new(1.0, 1.0, 3);
+        let now = Instant::now();
+        assert!(guard.check_at("room", "a", "10.0.0.1", now).is_ok());
+        assert!(guard.check_at("room", "a", "10.0.0.1", now).is_err());
+        assert!(guard.check_at("room", "b", "10.0.0.1", now).is_ok());
+        assert!(guard.check_at("other", "a", "10.0.0.1", now).is_ok());
+
+        guard.reset_room("room");
+        assert!(guard.check_at("room", "a", "10.0.0.1", now).is_ok());
+    }
+
+    #[test]
+    fn human_cadence_passes() {
+        let mut guard = ClickGuard::new(100.0, 100.0, 3);
+        let mut at = Instant::now();
+        for i in 0..(CADENCE_WINDOW as u64 * 3) {
+            at += human_gap(i);
+            assert!(guard.check_at("room", "alice", "10.0.0.1", at).is_ok(), "click {} rejected", i);
+        }
+    }
+
+    #[test]
+    fn metronome_cadence_is_rejected_and_disqualifies() {
+        let mut guard = ClickGuard::new(100.0, 100.0, 2);
+        let mut at = Instant::now();
+        let mut rejections = Vec::new();
+        for _ in 0..(CADENCE_WINDOW * 2) {
+            at += ms(100);
+            if let Err(rejection) = guard.check_at("room", "bot", "10.0.0.1", at) {
+                rejections.push(rejection);
+            }
+        }
+
+        assert_eq!(rejections.len(), 2);
+        assert!(rejections.iter().all(|r| r.reason == RejectReason::InhumanCadence));
+        assert!(rejections[0].flagged && !rejections[0].disqualified);
+        assert!(rejections[1].disqualified);
+
+        let after = guard.check_at("room", "bot", "10.0.0.1", at + Duration::from_secs(5)).unwrap_err();
+        assert_eq!(after.reason, RejectReason::Disqualified);
+        assert!(guard.check_at("room", "someone-else", "10.0.0.1", at).is_ok());
+    }
+
+    #[test]
+    fn too_fast_on_average_is_rejected() {
+        let mut guard = ClickGuard::new(1000.0, 1000.0, 3);
+        let mut at = Instant::now();
+        let mut reasons = Vec::new();
+        for i in 0..CADENCE_WINDOW as u64 {
+            // Jittery but averaging about 30ms between clicks
+            at += ms(20 + (i * 7) % 20);
+            if let Err(rejection) = guard.check_at("room", "fast", "10.0.0.1", at) {
+                reasons.push(rejection.reason);
+            }
+        }
+        assert_eq!(reasons, vec![RejectReason::InhumanCadence]);
+    }
+
+    #[test]
+    fn sustained_rate_limiting_earns_a_strike() {
+        let mut guard = ClickGuard::new(1.0, 1.0, 3);
+        let mut at = Instant::now();
+        let mut rate_limited = 0;
+        for i in 0..200u64 {
+            // Human-looking cadence, just far more clicks than the bucket allows
+            at += ms(50 + (i * 13) % 40);
+            let Err(rejection) = guard.check_at("room", "spammer", "10.0.0.1", at) else {
+                continue;
+            };
+            assert_eq!(rejection.reason, RejectReason::RateLimited);
+            rate_limited += 1;
+            assert_eq!(rejection.strikes, rate_limited / RATE_LIMITED_PER_STRIKE);
+            if rejection.strikes == 1 {
+                assert!(rejection.flagged && !rejection.disqualified);
+                return;
+            }
+        }
+        panic!("no strike after {} rate limited clicks", rate_limited);
+    }
+
+    #[test]
+    fn players_sharing_an_address_do_not_throttle_each_other() {
+        let mut guard = ClickGuard::new(5.0, 5.0, 3);
+        let mut at = Instant::now();
+        for i in 0..(CADENCE_WINDOW as u64 * 2) {
+            at += human_gap(i);
+            for player in ["alice", "bob", "carol"] {
+                assert!(
+                    guard.check_at("room", player, "127.0.0.1", at).is_ok(),
+                    "click {} by {} rejected",
+                    i,
+                    player
+                );
+            }
+        }
+
+        // A scripted player on the same address is disqualified on its own
+        let mut bot_at = at;
+        for _ in 0..(CADENCE_WINDOW * 3) {
+            bot_at += ms(100);
+            let _ = guard.check_at("room", "bot", "127.0.0.1", bot_at);
+        }
+        let bot = guard.check_at("room", "bot", "127.0.0.1", bot_at + ms(100)).unwrap_err();
+        assert_eq!(bot.reason, RejectReason::Disqualified);
+        assert!(guard.check_at("room", "alice", "127.0.0.1", bot_at + ms(150)).is_ok());
+    }
+
+    #[test]
+    fn rotating_names_runs_out_of_address_clicks() {
+        let mut guard = ClickGuard::new(1.0, 1.0, 3);
+        let now = Instant::now();
+        let allowed = (0..100)
+            .filter(|i| guard.check_at("room", &format!("player-{}", i), "10.0.0.9", now).is_ok())
+            .count();
+        assert_eq!(allowed, ADDRESS_SHARE as usize);
+
+        let rejection = guard.check_at("room", "yet-another-name", "10.0.0.9", now).unwrap_err();
+        assert_eq!(rejection.reason, RejectReason::RateLimited);
+        assert!(!rejection.flagged);
+        assert!(guard.check_at("room", "newcomer", "10.0.0.10", now).is_ok());
+    }
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+use lapin::message::Delivery;
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use tokio::sync::Mutex;
+use tracing::{info, warn, debug, instrument};
+
+// Also compiled into the standalone `worker` binary, so this module only depends on
+// external crates and not on the rest of the server.
+
+const DEFAULT_WINDOW_SECS: u64 = 3600;
+
+/// Redis key recording that `message_id` was processed by consumers in `namespace`.
+pub fn key(namespace: &str, message_id: &str) -> String {
+    format!("dedup:{}:{}", namespace, message_id)
+}
+
+/// Remembers which `message_id`s a consumer has already processed so redelivered
+/// messages can be acknowledged without being handled twice. Entries live in Redis
+/// as `dedup:<namespace>:<message_id>` keys that expire after the configured window.
+#[derive(Debug)]
+pub struct Deduplicator {
+    client: redis::Client,
+    connection: Mutex<Option<MultiplexedConnection>>,
+    namespace: String,
+    window: Duration,
+}
+
+impl Deduplicator {
+    pub fn new(client: redis::Client, namespace: &str, window: Duration) -> Self {
+        info!("Deduplicating {} messages over a {:?} window", namespace, window);
+        Self {
+            client,
+            connection: Mutex::new(None),
+            namespace: namespace.to_string(),
+            window,
+        }
+    }
+
+    /// Uses `DEDUP_WINDOW_SECS` for the window, defaulting to one hour.
+    pub fn from_env(client: redis::Client, namespace: &str) -> Self {
+        let window_secs = std::env::var("DEDUP_WINDOW_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_WINDOW_SECS);
+        Self::new(client, namespace, Duration::from_secs(window_secs))
+    }
+
+    /// Hands out the shared connection, opening it on first use or after a failure.
+    async fn connection(&self) -> redis::RedisResult<MultiplexedConnection> {
+        let mut connection = self.connection.lock().await;
+        if let Some(conn) = connection.as_ref() {
+            return Ok(conn.clone());
+        }
+        debug!("Opening dedup connection for {}", self.namespace);
+        let conn = self.client.get_multiplexed_tokio_connection().await?;
+        *connection = Some(conn.clone());
+        Ok(conn)
+    }
+
+    /// Drops the shared connection so the next call reconnects.
+    async fn reset_connection(&self) {
+        self.connection.lock().await.take();
+    }
+
+    /// Fails open: if Redis is unavailable the message is treated as new.
+    #[instrument(skip(self), fields(namespace = %self.namespace))]
+    pub async fn is_duplicate(&self, message_id: &str) -> bool {
+        let mut conn = match self.connection().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Skipping duplicate check for {}: {}", message_id, e);
+                return false;
+            }
+        };
+        match conn.exists::<_, bool>(key(&self.namespace, message_id)).await {
+            Ok(seen) => {
+                debug!("Message {} seen before: {}", message_id, seen);
+                seen
+            }
+            Err(e) => {
+                warn!("Failed to check message {} for duplicates: {}", message_id, e);
+                self.reset_connection().await;
+                false
+            }
+        }
+    }
+
+    #[instrument(skip(self), fields(namespace = %self.namespace))]
+    pub async fn mark_processed(&self, message_id: &str) {
+        let mut conn = match self.connection().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Could not record message {} as processed: {}", message_id, e);
+                return;
+            }
+        };
+        let result: redis::RedisResult<()> = conn
+            .set_ex(key(&self.namespace, message_id), chrono::Utc::now().to_rfc3339(), self.window.as_secs())
+            .await;
+        match result {
+            Ok(_) => debug!("Recorded message {} as processed", message_id),
+            Err(e) => {
+                warn!("Failed to record message {} as processed: {}", message_id, e);
+                self.reset_connection().await;
+            }
+        }
+    }
+}
+
+pub fn message_id(delivery: &Delivery) -> Option<String> {
+    delivery
+        .properties
+        .message_id()
+        .as_ref()
+        .map(|id| id.as_str().to_string())
+}
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use serde::Serialize;
+use warp::{Reply, Rejection};
+use crate::management::ManagementError;
+use crate::AppState;
+use tracing::{error, debug, instrument};
+
+/// Answer of the broker endpoints; `data` holds what the management API returned.
+#[derive(Serialize)]
+pub struct BrokerResponse<T: Serialize> {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn respond<T: Serialize>(what: &str, result: Result<T, ManagementError>) -> impl Reply {
+    let response = match result {
+        Ok(data) => {
+            debug!("Listed broker {}", what);
+            BrokerResponse { success: true, data: Some(data), error: None }
+        }
+        Err(e) => {
+            error!("Failed to list broker {}: {}", what, e);
+            BrokerResponse { success: false, data: None, error: Some(e.to_string()) }
+        }
+    };
+    warp::reply::json(&response)
+}
+
+#[instrument(skip(state))]
+pub async fn list_exchanges(state: Arc<AppState>) -> Result<impl Reply, Rejection> {
+    Ok(respond("exchanges", state.management.exchanges().await))
+}
+
+#[instrument(skip(state))]
+pub async fn list_bindings(state: Arc<AppState>) -> Result<impl Reply, Rejection> {
+    Ok(respond("bindings", state.management.bindings().await))
+}
+
+#[instrument(skip(state))]
+pub async fn list_channels(state: Arc<AppState>) -> Result<impl Reply, Rejection> {
+    Ok(respond("channels", state.management.channels().await))
+}
+
+#[instrument(skip(state))]
+pub async fn list_consumers(state: Arc<AppState>) -> Result<impl Reply, Rejection> {
+    Ok(respond("consumers", state.management.consumers().await))
+}
+
+/// Broker-wide publish, deliver and ack rates plus the rates of every queue.
+#[instrument(skip(state))]
+pub async fn get_rates(state: Arc<AppState>) -> Result<impl Reply, Rejection> {
+    let (overview, queues) = tokio::join!(state.management.rates(), state.management.queues());
+    let result = overview.and_then(|overview| {
+        let queues = queues?;
+        Ok(serde_json::json!({ "overview": overview, "queues": queues }))
+    });
+    Ok(respond("rates", result))
+}
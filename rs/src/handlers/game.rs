@@ -1,98 +1,866 @@
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
+use futures_util::StreamExt;
+use lapin::{options::BasicCancelOptions, Channel, Consumer};
 use warp::{Reply, Rejection};
 use serde::{Deserialize, Serialize};
-use crate::{AppState, WebSocketMessage};
+use tokio::time::{sleep, Duration};
+use crate::AppState;
+use crate::click_guard::{ClickRejection, RejectReason};
+use crate::redis_store::{ClickOutcome, GAME_LEADERBOARD_CLICKS, GAME_LEADERBOARD_WINS};
 use tracing::{info, error, warn, debug, instrument};
 
+pub const DEFAULT_ROOM: &str = "default";
+const WINNING_SCORE: u32 = 100;
+const COUNTDOWN_SECS: u64 = 3;
+const MAX_ROOM_NAME_LEN: usize = 32;
+const MAX_ROOMS: usize = 50;
+const GAME_EXCHANGE: &str = "game_scores";
+const DEFAULT_LEADERBOARD_LIMIT: usize = 10;
+const DEFAULT_ROUNDS_LIMIT: usize = 20;
+const MAX_QUERY_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundState {
+    Lobby,
+    Countdown,
+    Running,
+    Finished,
+}
+
+/// A named game with its own players, score table and round lifecycle
+/// (lobby -> countdown -> running -> finished, then reset back to lobby).
+///
+/// Rooms and their round state live in each server instance's memory. With several
+/// instances behind one Redis only the round numbers, click counts and the winner claim
+/// are shared; starting, resetting and the countdown only affect the instance handling
+/// the request.
+#[derive(Debug, Clone, Serialize)]
+pub struct GameRoom {
+    pub name: String,
+    pub state: RoundState,
+    pub round: u32,
+    pub players: BTreeSet<String>,
+    pub scores: HashMap<String, u32>,
+    pub disqualified: BTreeSet<String>,
+    pub winner: Option<String>,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl GameRoom {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            state: RoundState::Lobby,
+            round: 0,
+            players: BTreeSet::new(),
+            scores: HashMap::new(),
+            disqualified: BTreeSet::new(),
+            winner: None,
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
+    fn clear_round(&mut self) {
+        self.scores.clear();
+        self.disqualified.clear();
+        self.winner = None;
+        self.started_at = None;
+        self.finished_at = None;
+    }
+}
+
 #[derive(Deserialize)]
 pub struct ClickRequest {
     pub player_name: String,
+    pub room: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct ClickResponse {
     pub success: bool,
     pub score: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<ClickRejection>,
+}
+
+#[derive(Deserialize)]
+pub struct PlayerRequest {
+    pub player_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScoresQuery {
+    pub room: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct ScoresResponse {
-    pub scores: std::collections::HashMap<String, u32>,
+    pub room: String,
+    pub round: u32,
+    pub scores: HashMap<String, u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardQuery {
+    pub board: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct LeaderboardEntry {
+    pub player: String,
+    pub score: u64,
+}
+
+#[derive(Serialize)]
+pub struct LeaderboardResponse {
+    pub success: bool,
+    pub board: String,
+    pub entries: Vec<LeaderboardEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoundsQuery {
+    pub room: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct RoundsResponse {
+    pub success: bool,
+    pub rounds: Vec<RoundResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of a finished round as kept in the Redis round history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundResult {
+    pub room: String,
+    pub round: u32,
+    pub winner: Option<String>,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: Option<i64>,
+    pub clicks: HashMap<String, u32>,
+    pub total_clicks: u32,
+}
+
+impl RoundResult {
+    fn from_room(room: &GameRoom) -> Self {
+        let finished_at = room.finished_at.unwrap_or_else(chrono::Utc::now);
+        Self {
+            room: room.name.clone(),
+            round: room.round,
+            winner: room.winner.clone(),
+            started_at: room.started_at,
+            finished_at,
+            duration_ms: room.started_at.map(|started| (finished_at - started).num_milliseconds()),
+            clicks: room.scores.clone(),
+            total_clicks: room.scores.values().sum(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RoomResponse {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room: Option<GameRoom>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RoomsResponse {
+    pub rooms: Vec<GameRoom>,
+}
+
+impl RoomResponse {
+    fn ok(room: GameRoom) -> Self {
+        Self { success: true, room: Some(room), error: None }
+    }
+
+    fn error(error: impl Into<String>) -> Self {
+        Self { success: false, room: None, error: Some(error.into()) }
+    }
+}
+
+fn valid_room_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_ROOM_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
 }
 
 #[instrument(skip(req, state), fields(player = %req.player_name))]
 pub async fn handle_click(
     req: ClickRequest,
+    remote: Option<std::net::SocketAddr>,
     state: Arc<AppState>,
 ) -> Result<impl Reply, Rejection> {
-    info!("Player {} clicked", req.player_name);
-    
-    let mut scores = state.game_scores.lock().await;
-    let current_score = scores.entry(req.player_name.clone()).or_insert(0);
-    *current_score += 1;
-    let new_score = *current_score;
-    drop(scores);
-    
-    info!("Player {} new score: {}", req.player_name, new_score);
-
-    let score_update = serde_json::json!({
-        "player": req.player_name,
-        "score": new_score
-    });
-
-    if let Err(e) = state.rabbit.publish_to_exchange("game_scores", &score_update.to_string()).await {
-        error!("Failed to publish score update for {}: {}", req.player_name, e);
-    } else {
-        debug!("Score update published to RabbitMQ for player {}", req.player_name);
-    }
+    let room_name = req.room.unwrap_or_else(|| DEFAULT_ROOM.to_string());
+    info!("Player {} clicked in room {}", req.player_name, room_name);
 
-    let ws_msg = WebSocketMessage {
-        demo_type: "game".to_string(),
-        data: serde_json::json!({
-            "type": "score_update",
-            "player": req.player_name,
-            "score": new_score
-        }),
+    let round = {
+        let mut rooms = state.game_rooms.lock().await;
+        let Some(room) = rooms.get_mut(&room_name) else {
+            warn!("Click for unknown room {}", room_name);
+            return Ok(warp::reply::json(&ClickResponse {
+                success: false,
+                score: 0,
+                error: Some(format!("Room {} does not exist", room_name)),
+                rejection: None,
+            }));
+        };
+        if room.state != RoundState::Running {
+            debug!("Rejecting click in room {} while {:?}", room_name, room.state);
+            return Ok(warp::reply::json(&rejected_click(room, &req.player_name)));
+        }
+        room.players.insert(req.player_name.clone());
+        room.round
     };
 
-    if let Err(_) = state.broadcast_tx.send(ws_msg) {
-        warn!("No WebSocket clients for game score update");
-    } else {
-        debug!("Game score update broadcasted to WebSocket clients");
+    // Players behind one address (e.g. everyone on localhost) get their own buckets; the
+    // address only shares a looser one so switching names does not buy unlimited clicks
+    let address = remote.map_or_else(|| "unknown".to_string(), |addr| addr.ip().to_string());
+    let verdict = state.click_guard.lock().await.check(&room_name, &req.player_name, &address);
+    if let Err(rejection) = verdict {
+        return Ok(warp::reply::json(&reject_guarded_click(&state, &room_name, round, &req.player_name, rejection).await));
     }
 
-    if new_score >= 100 {
-        info!("Player {} reached winning score: {}", req.player_name, new_score);
-        let winner_msg = WebSocketMessage {
-            demo_type: "game".to_string(),
-            data: serde_json::json!({
-                "type": "winner",
-                "player": req.player_name,
-                "score": new_score
-            }),
+    // Redis decides the winner so several server instances agree on one; the room
+    // lock only stands in when Redis is unreachable
+    let persisted = match state.redis.record_game_click(&room_name, round, &req.player_name, WINNING_SCORE).await {
+        Ok(outcome) => Some(outcome),
+        Err(e) => {
+            warn!("Counting click by {} in room {} in memory only: {}", req.player_name, room_name, e);
+            None
+        }
+    };
+
+    let (outcome, finished, snapshot) = {
+        let mut rooms = state.game_rooms.lock().await;
+        let Some(room) = rooms.get_mut(&room_name).filter(|room| room.round == round) else {
+            debug!("Room {} moved past round {} before the click was counted", room_name, round);
+            return Ok(warp::reply::json(&ClickResponse {
+                success: false,
+                score: 0,
+                error: Some(format!("Round {} is over", round)),
+                rejection: None,
+            }));
         };
-        if let Err(_) = state.broadcast_tx.send(winner_msg) {
-            warn!("No WebSocket clients for winner announcement");
-        } else {
-            info!("Winner announcement broadcasted for player {}", req.player_name);
+        let (outcome, finished) = settle_click(room, &req.player_name, persisted);
+        (outcome, finished, room.clone())
+    };
+
+    let new_score = match outcome {
+        ClickOutcome::Counted { score } | ClickOutcome::Won { score } => score,
+        ClickOutcome::RoundOver { winner } => {
+            info!("Rejecting click by {}: room {} round {} already won by {}", req.player_name, room_name, round, winner);
+            if finished {
+                publish_room_state(&state, &snapshot).await;
+            }
+            return Ok(warp::reply::json(&rejected_click(&snapshot, &req.player_name)));
         }
+    };
+    info!("Player {} new score in room {}: {}", req.player_name, room_name, new_score);
+
+    publish_room_event(&state, &room_name, serde_json::json!({
+        "type": "score_update",
+        "room": room_name,
+        "round": round,
+        "player": req.player_name,
+        "score": new_score
+    })).await;
+
+    if let ClickOutcome::Won { .. } = outcome {
+        info!("Player {} reached winning score in room {}: {}", req.player_name, room_name, new_score);
+        publish_room_event(&state, &room_name, serde_json::json!({
+            "type": "winner",
+            "room": room_name,
+            "round": round,
+            "player": req.player_name,
+            "score": new_score
+        })).await;
+        publish_room_state(&state, &snapshot).await;
+        finish_round(&state, snapshot).await;
     }
 
     Ok(warp::reply::json(&ClickResponse {
         success: true,
         score: new_score,
+        error: None,
+        rejection: None,
     }))
 }
 
+fn rejected_click(room: &GameRoom, player: &str) -> ClickResponse {
+    let error = match (&room.state, &room.winner) {
+        (RoundState::Finished, Some(winner)) => format!("Round {} was already won by {}", room.round, winner),
+        (state, _) => format!("Round is not running (state: {:?})", state).to_lowercase(),
+    };
+    ClickResponse {
+        success: false,
+        score: room.scores.get(player).copied().unwrap_or(0),
+        error: Some(error),
+        rejection: None,
+    }
+}
+
+/// Turns a click the guard refused into a response, recording disqualifications on the
+/// room and reporting rejections to WebSocket clients.
+async fn reject_guarded_click(
+    state: &Arc<AppState>,
+    room_name: &str,
+    round: u32,
+    player: &str,
+    rejection: ClickRejection,
+) -> ClickResponse {
+    let (score, newly_disqualified) = {
+        let mut rooms = state.game_rooms.lock().await;
+        match rooms.get_mut(room_name).filter(|room| room.round == round) {
+            Some(room) => {
+                let newly = rejection.disqualified && room.disqualified.insert(player.to_string());
+                (room.scores.get(player).copied().unwrap_or(0), newly.then(|| room.clone()))
+            }
+            None => (0, None),
+        }
+    };
+
+    if rejection.report || newly_disqualified.is_some() {
+        publish_room_event(state, room_name, serde_json::json!({
+            "type": "click_rejected",
+            "room": room_name,
+            "round": round,
+            "player": player,
+            "reason": rejection.reason,
+            "strikes": rejection.strikes,
+            "flagged": rejection.flagged,
+            "disqualified": rejection.disqualified
+        })).await;
+    }
+    if let Some(snapshot) = newly_disqualified {
+        warn!("Player {} disqualified from room {} round {}", player, room_name, round);
+        publish_room_state(state, &snapshot).await;
+    }
+
+    let error = match rejection.reason {
+        RejectReason::RateLimited => "Clicking too fast, slow down".to_string(),
+        RejectReason::InhumanCadence => "Click pattern looks automated".to_string(),
+        RejectReason::Disqualified => format!("{} is disqualified for this round", player),
+    };
+    ClickResponse {
+        success: false,
+        score,
+        error: Some(error),
+        rejection: Some(rejection),
+    }
+}
+
+/// Applies a click to the in-memory room, following Redis' verdict when there is one.
+/// Also reports whether this click is what moved the room to `Finished`.
+fn settle_click(room: &mut GameRoom, player: &str, persisted: Option<ClickOutcome>) -> (ClickOutcome, bool) {
+    let outcome = match persisted {
+        Some(outcome) => outcome,
+        None if room.state != RoundState::Running => ClickOutcome::RoundOver {
+            winner: room.winner.clone().unwrap_or_default(),
+        },
+        None => {
+            let score = room.scores.get(player).copied().unwrap_or(0) + 1;
+            if score >= WINNING_SCORE {
+                ClickOutcome::Won { score }
+            } else {
+                ClickOutcome::Counted { score }
+            }
+        }
+    };
+
+    let winner = match &outcome {
+        ClickOutcome::Counted { score } => {
+            room.scores.insert(player.to_string(), *score);
+            None
+        }
+        ClickOutcome::Won { score } => {
+            room.scores.insert(player.to_string(), *score);
+            Some(player.to_string())
+        }
+        ClickOutcome::RoundOver { winner } => Some(winner.clone()),
+    };
+
+    let finished = winner.is_some() && room.state == RoundState::Running;
+    if finished {
+        room.state = RoundState::Finished;
+        room.winner = winner;
+        room.finished_at = Some(chrono::Utc::now());
+    }
+    (outcome, finished)
+}
+
 #[instrument(skip(state))]
 pub async fn get_scores(
+    query: ScoresQuery,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let room_name = query.room.unwrap_or_else(|| DEFAULT_ROOM.to_string());
+    debug!("Getting current game scores for room {}", room_name);
+
+    // Redis keeps the latest round's scores across restarts; memory is the fallback
+    let persisted = match state.redis.current_game_round(&room_name).await {
+        Ok(Some(round)) => state
+            .redis
+            .get_round_scores(&room_name, round)
+            .await
+            .map(|scores| (round, scores))
+            .ok(),
+        _ => None,
+    };
+    let (round, scores) = match persisted {
+        Some(persisted) => persisted,
+        None => {
+            debug!("Falling back to in-memory scores for room {}", room_name);
+            let rooms = state.game_rooms.lock().await;
+            rooms
+                .get(&room_name)
+                .map(|r| (r.round, r.scores.clone()))
+                .unwrap_or_default()
+        }
+    };
+    info!("Retrieved scores for {} players", scores.len());
+    Ok(warp::reply::json(&ScoresResponse { room: room_name, round, scores }))
+}
+
+#[instrument(skip(state))]
+pub async fn get_leaderboard(
+    query: LeaderboardQuery,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let board = query.board.unwrap_or_else(|| GAME_LEADERBOARD_WINS.to_string());
+    if board != GAME_LEADERBOARD_WINS && board != GAME_LEADERBOARD_CLICKS {
+        return Ok(warp::reply::json(&LeaderboardResponse {
+            success: false,
+            board,
+            entries: Vec::new(),
+            error: Some(format!("Unknown leaderboard, expected {} or {}", GAME_LEADERBOARD_WINS, GAME_LEADERBOARD_CLICKS)),
+        }));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LEADERBOARD_LIMIT).clamp(1, MAX_QUERY_LIMIT);
+
+    let response = match state.redis.get_game_leaderboard(&board, limit).await {
+        Ok(entries) => {
+            debug!("Retrieved {} {} leaderboard entries", entries.len(), board);
+            LeaderboardResponse {
+                success: true,
+                board,
+                entries: entries
+                    .into_iter()
+                    .map(|(player, score)| LeaderboardEntry { player, score })
+                    .collect(),
+                error: None,
+            }
+        }
+        Err(e) => LeaderboardResponse {
+            success: false,
+            board,
+            entries: Vec::new(),
+            error: Some(format!("Failed to read leaderboard: {}", e)),
+        },
+    };
+    Ok(warp::reply::json(&response))
+}
+
+#[instrument(skip(state))]
+pub async fn get_rounds(
+    query: RoundsQuery,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let limit = query.limit.unwrap_or(DEFAULT_ROUNDS_LIMIT).clamp(1, MAX_QUERY_LIMIT);
+
+    let response = match state.redis.get_round_results().await {
+        Ok(entries) => {
+            let rounds: Vec<RoundResult> = entries
+                .iter()
+                .filter_map(|entry| match serde_json::from_str::<RoundResult>(entry) {
+                    Ok(result) => Some(result),
+                    Err(e) => {
+                        warn!("Skipping unreadable round result: {}", e);
+                        None
+                    }
+                })
+                .filter(|result| query.room.as_ref().is_none_or(|room| &result.room == room))
+                .take(limit)
+                .collect();
+            debug!("Retrieved {} round results", rounds.len());
+            RoundsResponse { success: true, rounds, error: None }
+        }
+        Err(e) => RoundsResponse {
+            success: false,
+            rounds: Vec::new(),
+            error: Some(format!("Failed to read round history: {}", e)),
+        },
+    };
+    Ok(warp::reply::json(&response))
+}
+
+#[instrument(skip(state))]
+pub async fn list_rooms(
     state: Arc<AppState>,
 ) -> Result<impl Reply, Rejection> {
-    debug!("Getting current game scores");
-    let scores = state.game_scores.lock().await;
-    let score_count = scores.len();
-    info!("Retrieved scores for {} players", score_count);
-    Ok(warp::reply::json(&ScoresResponse {
-        scores: scores.clone(),
+    let mut rooms: Vec<GameRoom> = state.game_rooms.lock().await.values().cloned().collect();
+    rooms.sort_by(|a, b| a.name.cmp(&b.name));
+    debug!("Listing {} game rooms", rooms.len());
+    Ok(warp::reply::json(&RoomsResponse { rooms }))
+}
+
+#[instrument(skip(state))]
+pub async fn get_room(
+    room_name: String,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let room = state.game_rooms.lock().await.get(&room_name).cloned();
+    Ok(warp::reply::json(&match room {
+        Some(room) => RoomResponse::ok(room),
+        None => RoomResponse::error(format!("Room {} does not exist", room_name)),
     }))
-}
\ No newline at end of file
+}
+
+#[instrument(skip(req, state), fields(player = %req.player_name))]
+pub async fn join_room(
+    room_name: String,
+    req: PlayerRequest,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    if !valid_room_name(&room_name) {
+        return Ok(warp::reply::json(&RoomResponse::error(
+            "Room names are 1-32 letters, digits, '-' or '_'",
+        )));
+    }
+    if req.player_name.trim().is_empty() {
+        return Ok(warp::reply::json(&RoomResponse::error("Player name is required")));
+    }
+
+    let snapshot = {
+        let mut rooms = state.game_rooms.lock().await;
+        if !rooms.contains_key(&room_name) && rooms.len() >= MAX_ROOMS {
+            warn!("Refusing to create room {}: {} rooms already exist", room_name, rooms.len());
+            return Ok(warp::reply::json(&RoomResponse::error(format!(
+                "Too many rooms (max {}), join an existing one",
+                MAX_ROOMS
+            ))));
+        }
+        let room = rooms.entry(room_name.clone()).or_insert_with(|| {
+            info!("Creating game room {}", room_name);
+            GameRoom::new(&room_name)
+        });
+        room.players.insert(req.player_name.clone());
+        room.clone()
+    };
+    info!("Player {} joined room {}", req.player_name, room_name);
+
+    publish_room_event(&state, &room_name, serde_json::json!({
+        "type": "player_joined",
+        "room": room_name,
+        "player": req.player_name
+    })).await;
+    Ok(warp::reply::json(&RoomResponse::ok(snapshot)))
+}
+
+#[instrument(skip(req, state), fields(player = %req.player_name))]
+pub async fn leave_room(
+    room_name: String,
+    req: PlayerRequest,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let snapshot = {
+        let mut rooms = state.game_rooms.lock().await;
+        let Some(room) = rooms.get_mut(&room_name) else {
+            return Ok(warp::reply::json(&RoomResponse::error(format!("Room {} does not exist", room_name))));
+        };
+        room.players.remove(&req.player_name);
+        let snapshot = room.clone();
+        // Idle empty rooms are dropped so they do not count against MAX_ROOMS
+        let idle = matches!(room.state, RoundState::Lobby | RoundState::Finished);
+        if snapshot.players.is_empty() && idle && room_name != DEFAULT_ROOM {
+            info!("Removing empty game room {}", room_name);
+            rooms.remove(&room_name);
+            state.click_guard.lock().await.reset_room(&room_name);
+        }
+        snapshot
+    };
+    info!("Player {} left room {}", req.player_name, room_name);
+
+    publish_room_event(&state, &room_name, serde_json::json!({
+        "type": "player_left",
+        "room": room_name,
+        "player": req.player_name
+    })).await;
+    Ok(warp::reply::json(&RoomResponse::ok(snapshot)))
+}
+
+#[instrument(skip(state))]
+pub async fn start_round(
+    room_name: String,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    // Claim the lobby first so a concurrent start is refused, then allocate the round
+    // number without holding the rooms lock while Redis answers
+    let previous_round = {
+        let mut rooms = state.game_rooms.lock().await;
+        let Some(room) = rooms.get_mut(&room_name) else {
+            return Ok(warp::reply::json(&RoomResponse::error(format!("Room {} does not exist", room_name))));
+        };
+        if room.state != RoundState::Lobby {
+            return Ok(warp::reply::json(&RoomResponse::error(format!(
+                "Room {} can only start from the lobby, it is {:?}",
+                room_name, room.state
+            ).to_lowercase())));
+        }
+        room.state = RoundState::Countdown;
+        room.round
+    };
+    let round = match state.redis.next_game_round(&room_name).await {
+        Ok(round) => round,
+        Err(e) => {
+            warn!("Using in-memory round number for room {}: {}", room_name, e);
+            previous_round + 1
+        }
+    };
+
+    let snapshot = {
+        let mut rooms = state.game_rooms.lock().await;
+        match rooms.get_mut(&room_name) {
+            Some(room) if room.state == RoundState::Countdown && room.round == previous_round => {
+                room.round = round;
+                room.clear_round();
+                room.clone()
+            }
+            _ => {
+                info!("Room {} was reset before round {} could start", room_name, round);
+                return Ok(warp::reply::json(&RoomResponse::error(format!(
+                    "Room {} was reset before the round started",
+                    room_name
+                ))));
+            }
+        }
+    };
+    state.click_guard.lock().await.reset_room(&room_name);
+    info!("Room {} starting round {} in {}s", room_name, snapshot.round, COUNTDOWN_SECS);
+    publish_room_state(&state, &snapshot).await;
+
+    tokio::spawn(run_countdown(state.clone(), room_name, snapshot.round));
+    Ok(warp::reply::json(&RoomResponse::ok(snapshot)))
+}
+
+#[instrument(skip(state))]
+pub async fn reset_room(
+    room_name: String,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let snapshot = {
+        let mut rooms = state.game_rooms.lock().await;
+        let Some(room) = rooms.get_mut(&room_name) else {
+            return Ok(warp::reply::json(&RoomResponse::error(format!("Room {} does not exist", room_name))));
+        };
+        room.state = RoundState::Lobby;
+        room.clear_round();
+        room.clone()
+    };
+    state.click_guard.lock().await.reset_room(&room_name);
+    info!("Room {} reset to lobby", room_name);
+    publish_room_state(&state, &snapshot).await;
+    Ok(warp::reply::json(&RoomResponse::ok(snapshot)))
+}
+
+async fn run_countdown(state: Arc<AppState>, room_name: String, round: u32) {
+    for remaining in (1..=COUNTDOWN_SECS).rev() {
+        publish_room_event(&state, &room_name, serde_json::json!({
+            "type": "countdown",
+            "room": room_name,
+            "round": round,
+            "remaining": remaining
+        })).await;
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    let snapshot = {
+        let mut rooms = state.game_rooms.lock().await;
+        match rooms.get_mut(&room_name) {
+            // The room may have been reset while counting down
+            Some(room) if room.state == RoundState::Countdown && room.round == round => {
+                room.state = RoundState::Running;
+                room.started_at = Some(chrono::Utc::now());
+                room.clone()
+            }
+            _ => {
+                debug!("Countdown for room {} round {} abandoned", room_name, round);
+                return;
+            }
+        }
+    };
+    info!("Room {} round {} is running", room_name, round);
+    publish_room_state(&state, &snapshot).await;
+}
+
+/// Announces the final standings of a won round and stores its result. Scores come from
+/// Redis when possible so clicks counted by other server instances are included.
+async fn finish_round(state: &Arc<AppState>, mut room: GameRoom) {
+    match state.redis.get_round_scores(&room.name, room.round).await {
+        Ok(scores) if !scores.is_empty() => room.scores = scores,
+        Ok(_) => {}
+        Err(e) => warn!("Using in-memory standings for room {} round {}: {}", room.name, room.round, e),
+    }
+
+    let mut standings: Vec<(&String, &u32)> = room.scores.iter().collect();
+    standings.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let standings: Vec<serde_json::Value> = standings
+        .into_iter()
+        .enumerate()
+        .map(|(i, (player, score))| serde_json::json!({
+            "rank": i + 1,
+            "player": player,
+            "score": score
+        }))
+        .collect();
+    publish_room_event(state, &room.name, serde_json::json!({
+        "type": "final_standings",
+        "room": room.name,
+        "round": room.round,
+        "winner": room.winner,
+        "standings": standings
+    })).await;
+
+    let result = RoundResult::from_room(&room);
+    let result_json = match serde_json::to_string(&result) {
+        Ok(json) => json,
+        Err(e) => {
+            error!("Failed to serialize result of room {} round {}: {}", room.name, room.round, e);
+            return;
+        }
+    };
+    match state.redis.record_round_result(result.winner.as_deref(), &result_json).await {
+        Ok(_) => info!("Saved result of room {} round {}", room.name, room.round),
+        Err(e) => warn!("Result of room {} round {} not persisted: {}", room.name, room.round, e),
+    }
+}
+
+async fn publish_room_state(state: &Arc<AppState>, room: &GameRoom) {
+    publish_room_event(state, &room.name, serde_json::json!({
+        "type": "room_state",
+        "room": room.name,
+        "round": room.round,
+        "state": room.state,
+        "winner": room.winner,
+        "scores": room.scores,
+        "disqualified": room.disqualified
+    })).await;
+}
+
+/// Publishes a room event to the `game_scores` fanout exchange. Every WebSocket client's
+/// queue gets every room's events; `GameFeed` picks out the subscribed room by the
+/// event's `room` field.
+async fn publish_room_event(state: &Arc<AppState>, room_name: &str, event: serde_json::Value) {
+    match state.rabbit.publish_to_exchange(GAME_EXCHANGE, &event.to_string()).await {
+        Ok(_) => debug!("Game event for room {} published to RabbitMQ", room_name),
+        Err(e) => error!("Failed to publish game event for room {}: {}", room_name, e),
+    }
+}
+
+/// What a WebSocket client asked to follow: one room, or every room when `room` is `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameSubscription {
+    pub room: Option<String>,
+}
+
+impl GameSubscription {
+    /// Parses `{"action": "subscribe_game", "room": "..."}`; an empty or missing room
+    /// follows every room.
+    pub fn from_request(request: &serde_json::Value) -> Self {
+        Self {
+            room: request["room"].as_str().filter(|r| !r.is_empty()).map(str::to_string),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameSubscriber {
+    pub queue: String,
+    pub room: Option<String>,
+    pub subscribed_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// One WebSocket client's exclusive, auto-delete queue bound to `game_scores`.
+pub struct GameFeed {
+    channel: Channel,
+    consumer: Consumer,
+    queue: String,
+    room: Option<String>,
+}
+
+impl GameFeed {
+    #[instrument(skip(state))]
+    pub async fn subscribe(
+        state: &Arc<AppState>,
+        subscription: GameSubscription,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let (channel, queue, consumer) = state.rabbit.subscribe_on_new_channel(GAME_EXCHANGE).await?;
+        state.game_subscribers.lock().await.insert(queue.clone(), GameSubscriber {
+            queue: queue.clone(),
+            room: subscription.room.clone(),
+            subscribed_at: chrono::Utc::now(),
+        });
+        info!("Game subscriber queue {} bound to {} (room: {:?})", queue, GAME_EXCHANGE, subscription.room);
+        Ok(Self {
+            channel,
+            consumer,
+            queue,
+            room: subscription.room,
+        })
+    }
+
+    /// Waits for the next event for the subscribed room; `None` once the queue is gone.
+    pub async fn next_event(&mut self) -> Option<serde_json::Value> {
+        while let Some(delivery) = self.consumer.next().await {
+            let delivery = match delivery {
+                Ok(delivery) => delivery,
+                Err(e) => {
+                    warn!("Game subscriber queue {} failed: {}", self.queue, e);
+                    return None;
+                }
+            };
+            let event: serde_json::Value = match serde_json::from_slice(&delivery.data) {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Skipping unreadable game event on {}: {}", self.queue, e);
+                    continue;
+                }
+            };
+            if self.room.as_deref().is_none_or(|room| event["room"] == room) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    /// Cancels the consumer and closes the channel, which deletes the queue.
+    pub async fn close(self, state: &Arc<AppState>) {
+        state.game_subscribers.lock().await.remove(&self.queue);
+        if let Err(e) = self
+            .channel
+            .basic_cancel(self.consumer.tag().as_str(), BasicCancelOptions::default())
+            .await
+        {
+            debug!("Failed to cancel game subscriber {}: {}", self.queue, e);
+        }
+        if let Err(e) = self.channel.close(200, "Game subscriber left").await {
+            debug!("Failed to close game subscriber channel {}: {}", self.queue, e);
+        }
+        info!("Game subscriber queue {} removed", self.queue);
+    }
+}
@@ -1,12 +1,130 @@
 use std::sync::Arc;
 use warp::{Reply, Rejection};
 use serde::{Deserialize, Serialize};
-use crate::{AppState, WebSocketMessage};
+use futures_util::StreamExt;
+use tokio::time::Duration;
+use lapin::message::Delivery;
+use lapin::options::BasicAckOptions;
+use crate::{dedup, metrics, AppState, WebSocketMessage};
 use tracing::{info, error, warn, debug, instrument};
 
+const LOGGER_QUEUE: &str = "message_logger";
+const LOGGER_PREFETCH: u16 = 20;
+const HISTORY_DEFAULT_LIMIT: usize = 50;
+const HISTORY_MAX_LIMIT: usize = 500;
+const HISTORY_SCAN_BATCH: usize = 200;
+const HISTORY_MAX_SCAN_BATCHES: usize = 20;
+const DEFAULT_HISTORY_MAX_ENTRIES: usize = 10_000;
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl std::str::FromStr for Severity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "trace" => Ok(Severity::Trace),
+            "debug" => Ok(Severity::Debug),
+            "info" => Ok(Severity::Info),
+            "warn" | "warning" => Ok(Severity::Warn),
+            "error" => Ok(Severity::Error),
+            "fatal" => Ok(Severity::Fatal),
+            other => Err(format!(
+                "Invalid severity '{}', expected one of trace, debug, info, warn, error, fatal",
+                other
+            )),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct LogMessage {
     pub message: String,
+    pub severity: Option<String>,
+    pub source: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    /// When the client produced the event, RFC 3339.
+    pub timestamp: Option<String>,
+}
+
+/// What travels through `message_logger`.
+#[derive(Debug, Serialize, Deserialize)]
+struct LogEnvelope {
+    message: String,
+    severity: Severity,
+    source: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    fields: serde_json::Map<String, serde_json::Value>,
+    client_timestamp: Option<String>,
+    published_at: Option<i64>,
+}
+
+/// Per-connection selection of which `logger` events a WebSocket client receives.
+#[derive(Debug, Clone, Default)]
+pub struct LoggerFilter {
+    pub min_severity: Option<Severity>,
+    pub source: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct LoggerFilterRequest {
+    min_severity: Option<String>,
+    source: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl LoggerFilter {
+    /// Builds a filter from a client request such as
+    /// `{"action": "logger_filter", "min_severity": "error", "source": "billing"}`.
+    pub fn from_request(request: &serde_json::Value) -> Result<Self, String> {
+        let request: LoggerFilterRequest = serde_json::from_value(request.clone())
+            .map_err(|e| format!("Invalid logger filter: {}", e))?;
+        Ok(LoggerFilter {
+            min_severity: request.min_severity.as_deref().map(str::parse).transpose()?,
+            source: request.source.filter(|s| !s.is_empty()),
+            tags: request.tags,
+        })
+    }
+
+    /// Matches a `logger` event payload; every tag in the filter must be present.
+    pub fn matches(&self, event: &serde_json::Value) -> bool {
+        if let Some(min_severity) = self.min_severity {
+            let severity = event["severity"]
+                .as_str()
+                .and_then(|s| s.parse::<Severity>().ok())
+                .unwrap_or(Severity::Info);
+            if severity < min_severity {
+                return false;
+            }
+        }
+        if let Some(source) = &self.source {
+            if event["source"].as_str() != Some(source.as_str()) {
+                return false;
+            }
+        }
+        self.tags.iter().all(|tag| {
+            event["tags"]
+                .as_array()
+                .is_some_and(|tags| tags.iter().any(|t| t.as_str() == Some(tag.as_str())))
+        })
+    }
 }
 
 #[derive(Serialize)]
@@ -15,6 +133,38 @@ pub struct LogResponse {
     pub message: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct HistoryQuery {
+    /// Start of the time range, as epoch millis or RFC 3339.
+    pub from: Option<String>,
+    /// End of the time range, as epoch millis or RFC 3339.
+    pub to: Option<String>,
+    /// Case-insensitive text to look for in the message.
+    pub q: Option<String>,
+    pub min_severity: Option<String>,
+    pub source: Option<String>,
+    /// `next_cursor` from the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    /// Start of the time range, as epoch millis or RFC 3339.
+    pub from: Option<String>,
+    /// End of the time range, as epoch millis or RFC 3339.
+    pub to: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    pub success: bool,
+    pub entries: Vec<serde_json::Value>,
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 #[instrument(skip(msg, state), fields(message_len = msg.message.len()))]
 pub async fn send_message(
     msg: LogMessage,
@@ -22,27 +172,52 @@ pub async fn send_message(
 ) -> Result<impl Reply, Rejection> {
     info!("Received logger message request");
     debug!("Message content: {}", msg.message);
-    
-    match state.rabbit.publish_message("message_logger", &msg.message).await {
+
+    let severity = match msg.severity.as_deref().map(str::parse::<Severity>).transpose() {
+        Ok(severity) => severity.unwrap_or(Severity::Info),
+        Err(e) => {
+            warn!("Rejecting logger message: {}", e);
+            return Ok(warp::reply::json(&LogResponse {
+                success: false,
+                message: e,
+            }));
+        }
+    };
+    if let Some(timestamp) = &msg.timestamp {
+        if chrono::DateTime::parse_from_rfc3339(timestamp).is_err() {
+            warn!("Rejecting logger message with invalid timestamp: {}", timestamp);
+            return Ok(warp::reply::json(&LogResponse {
+                success: false,
+                message: format!("Invalid timestamp '{}', expected RFC 3339", timestamp),
+            }));
+        }
+    }
+
+    // The broadcast to WebSocket clients happens in the consumer, once the message has
+    // actually gone through the queue
+    let envelope = LogEnvelope {
+        message: msg.message,
+        severity,
+        source: msg.source.filter(|s| !s.is_empty()).unwrap_or_else(|| "web".to_string()),
+        tags: msg.tags,
+        fields: msg.fields,
+        client_timestamp: msg.timestamp,
+        published_at: Some(chrono::Utc::now().timestamp_millis()),
+    };
+    let envelope = match serde_json::to_string(&envelope) {
+        Ok(envelope) => envelope,
+        Err(e) => {
+            error!("Failed to serialize logger message: {}", e);
+            return Ok(warp::reply::json(&LogResponse {
+                success: false,
+                message: format!("Failed to send message: {}", e),
+            }));
+        }
+    };
+
+    match state.rabbit.publish_message(LOGGER_QUEUE, &envelope).await {
         Ok(_) => {
             info!("Message published to RabbitMQ successfully");
-            
-            let timestamp = chrono::Utc::now().to_rfc3339();
-            let ws_msg = WebSocketMessage {
-                demo_type: "logger".to_string(),
-                data: serde_json::json!({
-                    "message": msg.message,
-                    "timestamp": timestamp
-                }),
-            };
-            
-            if let Err(_) = state.broadcast_tx.send(ws_msg) {
-                warn!("No WebSocket clients connected for logger message");
-            } else {
-                debug!("Logger message broadcasted to WebSocket clients");
-            }
-            
-            info!("Logger message processed successfully");
             Ok(warp::reply::json(&LogResponse {
                 success: true,
                 message: "Message sent successfully".to_string(),
@@ -56,4 +231,448 @@ pub async fn send_message(
             }))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Consumes `message_logger` and is the only source of `logger` WebSocket events, so
+/// clients see exactly what went through the broker.
+#[instrument(skip(state))]
+pub async fn start_logger_consumer(state: Arc<AppState>) {
+    let (_channel, mut consumer) = match state.rabbit.consume_queue_with_prefetch(LOGGER_QUEUE, LOGGER_PREFETCH).await {
+        Ok(consumer) => consumer,
+        Err(e) => {
+            error!("Failed to start logger consumer: {}", e);
+            return;
+        }
+    };
+
+    info!("Logger consumer listening on '{}' queue", LOGGER_QUEUE);
+    while let Some(delivery_result) = consumer.next().await {
+        match delivery_result {
+            Ok(delivery) => handle_delivery(&state, delivery).await,
+            Err(e) => {
+                error!("Logger consumer delivery error: {}", e);
+                break;
+            }
+        }
+    }
+    warn!("Logger consumer ended");
+}
+
+async fn handle_delivery(state: &Arc<AppState>, delivery: Delivery) {
+    let message_id = dedup::message_id(&delivery);
+    if let Some(message_id) = &message_id {
+        if state.logger_dedup.is_duplicate(message_id).await {
+            info!("Suppressing duplicate logger message {}", message_id);
+            if let Err(e) = delivery.ack(BasicAckOptions::default()).await {
+                error!("Failed to ack duplicate logger message: {}", e);
+            }
+            return;
+        }
+    }
+
+    // Messages published before the JSON envelope was introduced are plain text
+    let envelope = serde_json::from_slice::<LogEnvelope>(&delivery.data).unwrap_or_else(|_| LogEnvelope {
+        message: String::from_utf8_lossy(&delivery.data).to_string(),
+        severity: Severity::Info,
+        source: "unknown".to_string(),
+        tags: Vec::new(),
+        fields: serde_json::Map::new(),
+        client_timestamp: None,
+        published_at: None,
+    });
+    let queue_latency = metrics::queue_wait_ms(envelope.published_at);
+    debug!("Consumed logger message {:?} after {:?}ms in queue", message_id, queue_latency);
+
+    let ws_msg = WebSocketMessage {
+        demo_type: "logger".to_string(),
+        data: serde_json::json!({
+            "message": envelope.message,
+            "severity": envelope.severity,
+            "source": envelope.source,
+            "tags": envelope.tags,
+            "fields": envelope.fields,
+            "client_timestamp": envelope.client_timestamp,
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "message_id": message_id,
+            "delivery_tag": delivery.delivery_tag,
+            "redelivered": delivery.redelivered,
+            "queue_latency_ms": queue_latency
+        }),
+    };
+
+    if let Err(e) = state.redis.append_logger_history(&ws_msg.data.to_string()).await {
+        warn!("Logger message {:?} was not added to history: {}", message_id, e);
+    }
+
+    if let Some(sink) = &state.log_sink {
+        if let Err(e) = sink.write(&ws_msg.data).await {
+            error!("Failed to write logger message {:?} to file sink: {}", message_id, e);
+        }
+    }
+
+    if state.broadcast_tx.send(ws_msg).is_err() {
+        debug!("No WebSocket clients connected for logger message");
+    }
+
+    // Recorded before the ack, so a message whose ack gets lost is suppressed when the
+    // broker redelivers it instead of being stored and broadcast a second time
+    if let Some(message_id) = &message_id {
+        state.logger_dedup.mark_processed(message_id).await;
+    }
+
+    if let Err(e) = delivery.ack(BasicAckOptions::default()).await {
+        error!("Failed to ack logger message: {}", e);
+    }
+}
+
+#[instrument(skip(state))]
+pub async fn get_history(
+    query: HistoryQuery,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let limit = query.limit.unwrap_or(HISTORY_DEFAULT_LIMIT).clamp(1, HISTORY_MAX_LIMIT);
+    let start = match query.from.as_deref().map(parse_history_time) {
+        Some(Some(ms)) => ms.to_string(),
+        Some(None) => return Ok(history_error("Invalid 'from' time")),
+        None => "-".to_string(),
+    };
+    let end = match (&query.cursor, query.to.as_deref().map(parse_history_time)) {
+        (Some(cursor), _) => format!("({}", cursor),
+        (None, Some(Some(ms))) => ms.to_string(),
+        (None, Some(None)) => return Ok(history_error("Invalid 'to' time")),
+        (None, None) => "+".to_string(),
+    };
+    let needle = query.q.as_deref().map(str::to_lowercase).filter(|q| !q.is_empty());
+    let filter = LoggerFilter {
+        min_severity: match query.min_severity.as_deref().map(str::parse).transpose() {
+            Ok(min_severity) => min_severity,
+            Err(e) => return Ok(history_error(&e)),
+        },
+        source: query.source.clone(),
+        tags: Vec::new(),
+    };
+
+    let matches = |entry: &serde_json::Value| {
+        filter.matches(entry) && needle.as_ref().is_none_or(|needle| {
+            entry["message"].as_str().is_some_and(|m| m.to_lowercase().contains(needle))
+        })
+    };
+    let read_batch = |end: String| {
+        let (state, start) = (state.clone(), start.clone());
+        async move { state.redis.read_logger_history(&start, &end, HISTORY_SCAN_BATCH).await }
+    };
+    let (entries, next_cursor) =
+        match history_page(read_batch, end, limit, HISTORY_SCAN_BATCH, HISTORY_MAX_SCAN_BATCHES, matches).await {
+            Ok(page) => page,
+            Err(e) => return Ok(history_error(&format!("Failed to read history: {}", e))),
+        };
+
+    info!("Returning {} logger history entries", entries.len());
+    Ok(warp::reply::json(&HistoryResponse {
+        success: true,
+        entries,
+        next_cursor,
+        error: None,
+    }))
+}
+
+/// Scans history newest-first, `batch_size` entries at a time below `end`, until `limit`
+/// entries match. Returns them with the cursor to resume from, which is `None` once
+/// nothing older is left.
+async fn history_page<F, Fut>(
+    mut read_batch: F,
+    mut end: String,
+    limit: usize,
+    batch_size: usize,
+    max_batches: usize,
+    matches: impl Fn(&serde_json::Value) -> bool,
+) -> redis::RedisResult<(Vec<serde_json::Value>, Option<String>)>
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = redis::RedisResult<Vec<(String, String)>>>,
+{
+    let mut entries = Vec::new();
+    let mut last_scanned = None;
+    for _ in 0..max_batches {
+        let batch = read_batch(end.clone()).await?;
+        let exhausted = batch.len() < batch_size;
+        let batch_len = batch.len();
+
+        for (index, (id, entry_json)) in batch.into_iter().enumerate() {
+            last_scanned = Some(id.clone());
+            let Ok(mut entry) = serde_json::from_str::<serde_json::Value>(&entry_json) else {
+                warn!("Skipping unreadable history entry {}", id);
+                continue;
+            };
+            if matches(&entry) {
+                entry["id"] = serde_json::Value::String(id.clone());
+                entries.push(entry);
+                if entries.len() == limit {
+                    let at_end = exhausted && index + 1 == batch_len;
+                    return Ok((entries, (!at_end).then_some(id)));
+                }
+            }
+        }
+
+        if exhausted {
+            return Ok((entries, None));
+        }
+        match &last_scanned {
+            Some(id) => end = format!("({}", id),
+            None => return Ok((entries, None)),
+        }
+    }
+    // Scan budget ran out before the page filled; the cursor lets the client resume
+    Ok((entries, last_scanned))
+}
+
+fn history_error(message: &str) -> warp::reply::Json {
+    warn!("Logger history request failed: {}", message);
+    warp::reply::json(&HistoryResponse {
+        success: false,
+        entries: Vec::new(),
+        next_cursor: None,
+        error: Some(message.to_string()),
+    })
+}
+
+fn parse_history_time(value: &str) -> Option<i64> {
+    value.parse::<i64>().ok().or_else(|| {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|t| t.timestamp_millis())
+    })
+}
+
+/// Periodically trims the logger history. `LOGGER_HISTORY_MAX_ENTRIES` caps the number
+/// of stored messages (default 10000) and `LOGGER_HISTORY_MAX_AGE_SECS`, when set, drops
+/// messages older than that.
+#[instrument(skip(state))]
+pub async fn start_history_retention(state: Arc<AppState>) {
+    let max_entries = std::env::var("LOGGER_HISTORY_MAX_ENTRIES")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_HISTORY_MAX_ENTRIES);
+    let max_age_secs = std::env::var("LOGGER_HISTORY_MAX_AGE_SECS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok());
+    info!("Logger history retention: max {} entries, max age {:?}s", max_entries, max_age_secs);
+
+    let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        match state.redis.trim_logger_history_by_count(max_entries).await {
+            Ok(removed) if removed > 0 => info!("Trimmed {} logger history entries over the count limit", removed),
+            Ok(_) => {}
+            Err(e) => warn!("Logger history count retention failed: {}", e),
+        }
+
+        if let Some(max_age_secs) = max_age_secs {
+            let oldest_ms = chrono::Utc::now().timestamp_millis() - max_age_secs * 1000;
+            match state.redis.trim_logger_history_by_age(oldest_ms).await {
+                Ok(removed) if removed > 0 => info!("Trimmed {} logger history entries over the age limit", removed),
+                Ok(_) => {}
+                Err(e) => warn!("Logger history age retention failed: {}", e),
+            }
+        }
+    }
+}
+
+#[instrument(skip(state))]
+pub async fn export_log_file(
+    query: ExportQuery,
+    state: Arc<AppState>,
+) -> Result<warp::reply::Response, Rejection> {
+    let Some(sink) = &state.log_sink else {
+        return Ok(warp::reply::json(&LogResponse {
+            success: false,
+            message: "File sink is disabled, set LOGGER_SINK_DIR to enable it".to_string(),
+        }).into_response());
+    };
+
+    let (from_ms, to_ms) = match (
+        query.from.as_deref().map(parse_history_time),
+        query.to.as_deref().map(parse_history_time),
+    ) {
+        (Some(None), _) | (_, Some(None)) => {
+            return Ok(warp::reply::json(&LogResponse {
+                success: false,
+                message: "Invalid time range".to_string(),
+            }).into_response());
+        }
+        (from, to) => (from.flatten(), to.flatten()),
+    };
+
+    match sink.export(from_ms, to_ms).await {
+        Ok(chunks) => {
+            info!("Streaming logger messages from {:?} to {:?}", from_ms, to_ms);
+            let filename = format!("logger-{}.jsonl", chrono::Utc::now().format("%Y%m%dT%H%M%S"));
+            let body = warp::reply::Response::new(warp::hyper::Body::wrap_stream(chunks));
+            Ok(warp::reply::with_header(
+                warp::reply::with_header(body, "content-type", "application/x-ndjson"),
+                "content-disposition",
+                format!("attachment; filename=\"{}\"", filename),
+            ).into_response())
+        }
+        Err(e) => {
+            error!("Failed to export logger messages: {}", e);
+            Ok(warp::reply::json(&LogResponse {
+                success: false,
+                message: format!("Failed to export messages: {}", e),
+            }).into_response())
+        }
+    }
+}
+
+/// Gives the file sink a chance to rotate on time even when no messages arrive.
+#[instrument(skip(state))]
+pub async fn start_sink_rotation(state: Arc<AppState>) {
+    let Some(sink) = state.log_sink.clone() else {
+        return;
+    };
+    let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+    loop {
+        interval.tick().await;
+        sink.rotate_if_due().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ids `1-0..=count-0`, read like `XREVRANGE <end> - COUNT <batch>`.
+    fn history_stream(count: u64) -> Vec<(String, String)> {
+        (1..=count)
+            .map(|n| (format!("{}-0", n), serde_json::json!({ "message": format!("entry {}", n), "n": n }).to_string()))
+            .collect()
+    }
+
+    fn read_before(stream: &[(String, String)], end: &str, batch_size: usize) -> Vec<(String, String)> {
+        let seq = |id: &str| id.split('-').next().and_then(|n| n.parse::<u64>().ok()).unwrap_or(0);
+        let below = end.strip_prefix('(').map(seq);
+        stream
+            .iter()
+            .rev()
+            .filter(|(id, _)| below.is_none_or(|below| seq(id) < below))
+            .take(batch_size)
+            .cloned()
+            .collect()
+    }
+
+    async fn read_all_pages(
+        stream: &[(String, String)],
+        limit: usize,
+        batch_size: usize,
+        max_batches: usize,
+        matches: impl Fn(&serde_json::Value) -> bool + Copy,
+    ) -> (Vec<u64>, Vec<Option<String>>) {
+        let mut seen = Vec::new();
+        let mut cursors = Vec::new();
+        let mut end = "+".to_string();
+        for _ in 0..50 {
+            let read_batch = |end: String| {
+                let batch = read_before(stream, &end, batch_size);
+                async move { Ok(batch) }
+            };
+            let (entries, cursor) = history_page(read_batch, end, limit, batch_size, max_batches, matches)
+                .await
+                .unwrap();
+            seen.extend(entries.iter().map(|e| e["n"].as_u64().unwrap()));
+            cursors.push(cursor.clone());
+            match cursor {
+                Some(cursor) => end = format!("({}", cursor),
+                None => break,
+            }
+        }
+        (seen, cursors)
+    }
+
+    #[tokio::test]
+    async fn paging_to_the_end_returns_each_entry_once() {
+        let stream = history_stream(10);
+        // Only the newest and the oldest entries match, so a page spans a full batch
+        // that matches nothing and ends on a short, exhausted one
+        let (seen, cursors) = read_all_pages(&stream, 5, 3, 20, |e| matches!(e["n"].as_u64(), Some(1 | 10))).await;
+        assert_eq!(seen, vec![10, 1]);
+        assert_eq!(cursors, vec![None]);
+    }
+
+    #[tokio::test]
+    async fn cursor_resumes_when_the_scan_budget_runs_out() {
+        let stream = history_stream(10);
+        let (seen, cursors) = read_all_pages(&stream, 3, 2, 2, |e| e["n"].as_u64().unwrap() % 2 == 1).await;
+        assert_eq!(seen, vec![9, 7, 5, 3, 1]);
+        assert_eq!(cursors.last(), Some(&None));
+        assert!(cursors[..cursors.len() - 1].iter().all(Option::is_some));
+    }
+
+    #[tokio::test]
+    async fn full_page_ending_the_stream_has_no_cursor() {
+        let stream = history_stream(4);
+        let (seen, cursors) = read_all_pages(&stream, 2, 3, 20, |_| true).await;
+        assert_eq!(seen, vec![4, 3, 2, 1]);
+        assert_eq!(cursors, vec![Some("3-0".to_string()), None]);
+    }
+
+    #[test]
+    fn severity_parsing_ignores_case() {
+        assert_eq!("ERROR".parse::<Severity>(), Ok(Severity::Error));
+        assert_eq!("Warn".parse::<Severity>(), Ok(Severity::Warn));
+        assert_eq!("warning".parse::<Severity>(), Ok(Severity::Warn));
+        assert_eq!("trace".parse::<Severity>(), Ok(Severity::Trace));
+    }
+
+    #[test]
+    fn unknown_severity_is_rejected() {
+        let err = "critical".parse::<Severity>().unwrap_err();
+        assert!(err.contains("'critical'"), "{}", err);
+        assert!("".parse::<Severity>().is_err());
+
+        let request = serde_json::json!({ "min_severity": "loud" });
+        assert!(LoggerFilter::from_request(&request).is_err());
+    }
+
+    #[test]
+    fn severities_are_ordered() {
+        assert!(Severity::Trace < Severity::Debug);
+        assert!(Severity::Info < Severity::Warn);
+        assert!(Severity::Error < Severity::Fatal);
+    }
+
+    #[test]
+    fn min_severity_keeps_that_level_and_above() {
+        let filter = LoggerFilter::from_request(&serde_json::json!({ "min_severity": "Warn" })).unwrap();
+        assert!(!filter.matches(&serde_json::json!({ "severity": "info" })));
+        assert!(filter.matches(&serde_json::json!({ "severity": "warn" })));
+        assert!(filter.matches(&serde_json::json!({ "severity": "FATAL" })));
+        // Events without a readable severity count as info
+        assert!(!filter.matches(&serde_json::json!({ "severity": "verbose" })));
+        assert!(!filter.matches(&serde_json::json!({})));
+
+        let filter = LoggerFilter::from_request(&serde_json::json!({ "min_severity": "info" })).unwrap();
+        assert!(filter.matches(&serde_json::json!({})));
+        assert!(!filter.matches(&serde_json::json!({ "severity": "debug" })));
+    }
+
+    #[test]
+    fn source_and_tags_must_all_match() {
+        let filter = LoggerFilter::from_request(&serde_json::json!({
+            "source": "billing",
+            "tags": ["payments", "eu"]
+        }))
+        .unwrap();
+        assert!(filter.matches(&serde_json::json!({ "source": "billing", "tags": ["eu", "payments", "retry"] })));
+        assert!(!filter.matches(&serde_json::json!({ "source": "billing", "tags": ["payments"] })));
+        assert!(!filter.matches(&serde_json::json!({ "source": "Billing", "tags": ["payments", "eu"] })));
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let filter = LoggerFilter::from_request(&serde_json::json!({ "source": "" })).unwrap();
+        assert!(filter.source.is_none());
+        assert!(filter.matches(&serde_json::json!({ "severity": "trace", "source": "web" })));
+        assert!(LoggerFilter::default().matches(&serde_json::json!({})));
+    }
+}
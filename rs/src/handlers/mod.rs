@@ -3,4 +3,5 @@ pub mod workers;
 pub mod game;
 pub mod rpc;
 pub mod simulator;
-pub mod collaborative_drawing;
\ No newline at end of file
+pub mod collaborative_drawing;
+pub mod broker;
\ No newline at end of file
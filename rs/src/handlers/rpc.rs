@@ -1,18 +1,255 @@
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use warp::{Reply, Rejection};
+use warp::http::StatusCode;
+use warp::hyper::body::Bytes;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::time::Duration;
+use crate::heartbeats::{self, HasWorkers, HeartbeatSource, Heartbeating, WorkerRegistry};
 use crate::{AppState, WebSocketMessage};
+use crate::rabbitmq::{RemoteError, RpcError, RpcPart, RpcReply};
 use tracing::{info, error, warn, debug, instrument};
 
 #[derive(Deserialize)]
 pub struct StatusRequest {}
 
+#[derive(Serialize)]
+pub struct MethodResponse {
+    pub success: bool,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_details: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worker_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Progress parts relayed over WebSocket before the final reply.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parts: Option<u32>,
+    /// Tags every WebSocket event of this call, so a client can pick out its own progress.
+    pub request_id: String,
+}
+
 #[derive(Serialize)]
 pub struct StatusResponse {
     pub success: bool,
     pub timestamp: String,
     pub status: String,
-    pub server_info: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_details: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worker_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// The worker's reply exactly as it was received.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<serde_json::Value>,
+}
+
+const RPC_HEARTBEATS: HeartbeatSource = HeartbeatSource {
+    exchange: "rpc_heartbeats",
+    label: "RPC worker",
+    demo_type: "rpc",
+    event_prefix: "rpc_worker",
+    timeout: Duration::from_secs(6),
+};
+/// How many answered calls `GET /api/rpc/workers` remembers.
+const RECENT_CALLS: usize = 50;
+const JSONRPC_PARSE_ERROR: i64 = -32700;
+const JSONRPC_INTERNAL_ERROR: i64 = -32603;
+/// Start of the range JSON-RPC leaves for application errors, used for timeouts.
+const JSONRPC_SERVER_ERROR: i64 = -32000;
+const DEFAULT_SCATTER_TIMEOUT_MS: u64 = 2000;
+const MAX_SCATTER_TIMEOUT_MS: u64 = 30_000;
+
+/// A live `rpc_worker` instance as last reported by its heartbeat.
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcWorker {
+    pub worker_id: String,
+    pub host: String,
+    pub pid: u64,
+    pub started_at: String,
+    pub uptime_secs: i64,
+    pub processed: u64,
+    pub failed: u64,
+    pub in_flight: u64,
+    pub concurrency: u64,
+    /// Calls made through this server that the worker answered.
+    pub served: u64,
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+}
+
+impl Heartbeating for RpcWorker {
+    fn worker_id(&self) -> &str {
+        &self.worker_id
+    }
+
+    fn last_seen(&self) -> chrono::DateTime<chrono::Utc> {
+        self.last_seen
+    }
+}
+
+/// Which worker answered an RPC call made through this server.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServedCall {
+    pub method: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worker_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    pub at: String,
+}
+
+/// Live RPC workers, how many calls each has served and the most recent calls.
+#[derive(Debug, Default)]
+pub struct RpcWorkerRegistry {
+    workers: WorkerRegistry<RpcWorker>,
+    served: HashMap<String, u64>,
+    recent_calls: VecDeque<ServedCall>,
+}
+
+impl RpcWorkerRegistry {
+    fn record_call(&mut self, call: ServedCall) {
+        if let Some(worker_id) = &call.worker_id {
+            *self.served.entry(worker_id.clone()).or_default() += 1;
+        }
+        if self.recent_calls.len() == RECENT_CALLS {
+            self.recent_calls.pop_back();
+        }
+        self.recent_calls.push_front(call);
+    }
+
+    pub fn workers(&self) -> Vec<RpcWorker> {
+        self.workers
+            .workers()
+            .into_iter()
+            .map(|mut w| {
+                w.served = self.served.get(&w.worker_id).copied().unwrap_or(0);
+                w
+            })
+            .collect()
+    }
+}
+
+impl HasWorkers for RpcWorkerRegistry {
+    type Worker = RpcWorker;
+
+    fn registry(&mut self) -> &mut WorkerRegistry<RpcWorker> {
+        &mut self.workers
+    }
+}
+
+#[derive(Serialize)]
+pub struct RpcWorkersResponse {
+    pub workers: Vec<RpcWorker>,
+    /// Calls served per worker id, including workers that are no longer live.
+    pub served: HashMap<String, u64>,
+    pub recent_calls: Vec<ServedCall>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScatterRequest {
+    pub method: String,
+    #[serde(default)]
+    pub params: Option<serde_json::Value>,
+    /// How long to wait for replies; defaults to 2000ms.
+    pub timeout_ms: Option<u64>,
+    /// Stop once this many replies arrived; defaults to the number of live RPC workers.
+    pub expected: Option<usize>,
+}
+
+/// One worker's answer to a scatter call.
+#[derive(Serialize)]
+pub struct ScatterEntry {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worker_id: Option<String>,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ScatterResponse {
+    pub success: bool,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<usize>,
+    pub received: usize,
+    pub timed_out: bool,
+    pub elapsed_ms: u64,
+    pub responses: Vec<ScatterEntry>,
+    /// Live RPC workers that did not reply before the deadline.
+    pub missing: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+}
+
+impl ScatterResponse {
+    fn failed(method: String, error: String, error_code: &str) -> Self {
+        Self {
+            success: false,
+            method,
+            correlation_id: None,
+            expected: None,
+            received: 0,
+            timed_out: false,
+            elapsed_ms: 0,
+            responses: Vec::new(),
+            missing: Vec::new(),
+            error: Some(error),
+            error_code: Some(error_code.to_string()),
+        }
+    }
+}
+
+impl ScatterEntry {
+    fn from_reply(reply: &RpcReply) -> Self {
+        match RemoteError::from_reply(reply) {
+            Some(remote) => Self {
+                success: false,
+                worker_id: reply.worker_id.clone(),
+                latency_ms: reply.latency_ms,
+                result: None,
+                error: Some(remote.message),
+                error_code: Some(remote.code),
+            },
+            None => Self {
+                success: true,
+                worker_id: reply.worker_id.clone(),
+                latency_ms: reply.latency_ms,
+                result: reply.payload.get("result").cloned(),
+                error: None,
+                error_code: None,
+            },
+        }
+    }
 }
 
 #[instrument(skip(state))]
@@ -28,16 +265,26 @@ pub async fn check_status(
     });
     
     debug!("Making RPC call for status check");
-    match state.rabbit.rpc_call(request_data.clone()).await {
-        Ok(response) => {
-            info!("RPC status check completed successfully");
-            
+    let result = state.rabbit.rpc_call(request_data.clone()).await;
+    record_call(&state, "status", &result).await;
+    match result {
+        Ok(reply) => {
+            info!(
+                "RPC status check answered by {:?} in {}ms",
+                reply.worker_id, reply.latency_ms
+            );
+            let payload = &reply.payload;
+            let success = payload["success"].as_bool().unwrap_or(false);
+
             let ws_msg = WebSocketMessage {
                 demo_type: "rpc".to_string(),
                 data: serde_json::json!({
                     "type": "status_response",
                     "request": request_data,
-                    "response": response
+                    "response": payload,
+                    "worker_id": reply.worker_id,
+                    "correlation_id": reply.correlation_id,
+                    "latency_ms": reply.latency_ms
                 }),
             };
 
@@ -47,39 +294,52 @@ pub async fn check_status(
                 debug!("RPC status response broadcasted to WebSocket clients");
             }
 
-            let status_messages = [
-                "All systems operational",
-                "Running smoothly",
-                "Services healthy",
-                "Everything looks good",
-                "System status: OK",
-            ];
-
-            let random_status = status_messages[rand::random::<usize>() % status_messages.len()];
-            info!("Returning status: {}", random_status);
-
+            let status = payload["result"]["status"]
+                .as_str()
+                .unwrap_or("No status in reply")
+                .to_string();
             Ok(warp::reply::json(&StatusResponse {
-                success: true,
-                timestamp: chrono::Utc::now().to_rfc3339(),
-                status: random_status.to_string(),
-                server_info: "RabbitMQ Demo Server v1.0".to_string(),
+                success,
+                timestamp: payload["timestamp"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+                status,
+                error_code: None,
+                error_details: None,
+                server_info: payload["result"]["server_info"].as_str().map(str::to_string),
+                worker_id: reply.worker_id.clone(),
+                correlation_id: Some(reply.correlation_id.clone()),
+                latency_ms: Some(reply.latency_ms),
+                response: Some(reply.payload),
             }))
         }
         Err(e) => {
             error!("RPC status check failed: {}", e);
             
+            let remote = remote_context(&e);
             let error_response = StatusResponse {
                 success: false,
                 timestamp: chrono::Utc::now().to_rfc3339(),
                 status: format!("RPC Error: {}", e),
-                server_info: "RabbitMQ Demo Server v1.0".to_string(),
+                error_code: Some(e.code().to_string()),
+                error_details: remote.details.clone(),
+                server_info: None,
+                worker_id: remote.worker_id.clone(),
+                correlation_id: remote.correlation_id.clone(),
+                latency_ms: remote.latency_ms,
+                response: None,
             };
 
             let ws_msg = WebSocketMessage {
                 demo_type: "rpc".to_string(),
                 data: serde_json::json!({
                     "type": "status_error",
-                    "error": e.to_string()
+                    "error": e.to_string(),
+                    "code": e.code(),
+                    "details": remote.details,
+                    "worker_id": remote.worker_id,
+                    "correlation_id": remote.correlation_id
                 }),
             };
 
@@ -92,4 +352,494 @@ pub async fn check_status(
             Ok(warp::reply::json(&error_response))
         }
     }
-}
\ No newline at end of file
+}
+
+/// What is known about the call behind an RPC error; only remote errors have a reply.
+#[derive(Default)]
+struct ErrorContext {
+    details: Option<serde_json::Value>,
+    worker_id: Option<String>,
+    correlation_id: Option<String>,
+    latency_ms: Option<u64>,
+}
+
+fn remote_context(e: &RpcError) -> ErrorContext {
+    match e {
+        RpcError::Remote(remote) => ErrorContext {
+            details: remote.details.clone(),
+            worker_id: remote.worker_id.clone(),
+            correlation_id: Some(remote.correlation_id.clone()),
+            latency_ms: Some(remote.latency_ms),
+        },
+        RpcError::Timeout { correlation_id, .. } => ErrorContext {
+            correlation_id: Some(correlation_id.clone()),
+            ..Default::default()
+        },
+        RpcError::Transport(_) => ErrorContext::default(),
+    }
+}
+
+fn valid_method_name(method: &str) -> bool {
+    !method.is_empty()
+        && method.len() <= 64
+        && method.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// The caller's `X-Request-Id` when it is usable as a tag, otherwise a fresh one.
+fn request_id(header: Option<String>) -> String {
+    header
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 64
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Forwards `params` to the RPC worker as a call to `method` and relays its reply.
+/// Progress and reply events carry the request id, taken from `X-Request-Id` when the
+/// caller sets one so it can follow its own call before the reply arrives.
+#[instrument(skip(params, state))]
+pub async fn call_method(
+    method: String,
+    request_id_header: Option<String>,
+    params: serde_json::Value,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let request_id = request_id(request_id_header);
+    info!("Received RPC gateway request {} for method {}", request_id, method);
+    if !valid_method_name(&method) {
+        warn!("Rejected RPC method name {:?}", method);
+        return Ok(warp::reply::json(&MethodResponse {
+            success: false,
+            method,
+            result: None,
+            error: Some("Method names are lowercase letters, digits and '_'".to_string()),
+            error_code: Some("invalid_method".to_string()),
+            error_details: None,
+            worker_id: None,
+            correlation_id: None,
+            latency_ms: None,
+            parts: None,
+            request_id,
+        }));
+    }
+
+    let request_data = serde_json::json!({
+        "type": method,
+        "params": params,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    });
+
+    debug!("Making RPC call for method {}", method);
+    let (parts_tx, parts_rx) = mpsc::unbounded_channel();
+    let relay = tokio::spawn(relay_progress(state.clone(), method.clone(), request_id.clone(), parts_rx));
+    let result = state.rabbit.rpc_call_streaming(request_data.clone(), parts_tx).await;
+    // Every part has been relayed before the final reply goes out
+    let _ = relay.await;
+    record_call(&state, &method, &result).await;
+    let (response, ws_data) = match result {
+        Ok(reply) => {
+            let payload = &reply.payload;
+            info!("RPC method {} completed in {}ms", method, reply.latency_ms);
+            let response = MethodResponse {
+                success: true,
+                method: method.clone(),
+                result: payload.get("result").cloned(),
+                error: None,
+                error_code: None,
+                error_details: None,
+                worker_id: reply.worker_id.clone(),
+                correlation_id: Some(reply.correlation_id.clone()),
+                latency_ms: Some(reply.latency_ms),
+                parts: (reply.parts > 0).then_some(reply.parts),
+                request_id: request_id.clone(),
+            };
+            (response, serde_json::json!({
+                "type": "method_response",
+                "method": method,
+                "request_id": request_id,
+                "request": request_data,
+                "response": payload,
+                "worker_id": reply.worker_id,
+                "correlation_id": reply.correlation_id,
+                "latency_ms": reply.latency_ms
+            }))
+        }
+        Err(e) => {
+            error!("RPC method {} failed: {}", method, e);
+            let remote = remote_context(&e);
+            let response = MethodResponse {
+                success: false,
+                method: method.clone(),
+                result: None,
+                error: Some(match &e {
+                    RpcError::Remote(remote) => remote.message.clone(),
+                    other => format!("RPC Error: {}", other),
+                }),
+                error_code: Some(e.code().to_string()),
+                error_details: remote.details.clone(),
+                worker_id: remote.worker_id.clone(),
+                correlation_id: remote.correlation_id.clone(),
+                latency_ms: remote.latency_ms,
+                parts: None,
+                request_id: request_id.clone(),
+            };
+            (response, serde_json::json!({
+                "type": "method_error",
+                "method": method,
+                "request_id": request_id,
+                "request": request_data,
+                "error": e.to_string(),
+                "code": e.code(),
+                "details": remote.details,
+                "worker_id": remote.worker_id,
+                "correlation_id": remote.correlation_id
+            }))
+        }
+    };
+
+    let ws_msg = WebSocketMessage {
+        demo_type: "rpc".to_string(),
+        data: ws_data,
+    };
+    if state.broadcast_tx.send(ws_msg).is_err() {
+        warn!("No WebSocket clients for RPC method response");
+    } else {
+        debug!("RPC method response broadcasted to WebSocket clients");
+    }
+
+    Ok(warp::reply::json(&response))
+}
+
+/// Sends one request to every RPC worker and returns all replies that arrived in time,
+/// reporting the live workers that stayed silent as `missing`.
+#[instrument(skip(state))]
+pub async fn scatter(
+    req: ScatterRequest,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    info!("Received RPC scatter request for method {}", req.method);
+    if !valid_method_name(&req.method) {
+        warn!("Rejected RPC method name {:?}", req.method);
+        return Ok(warp::reply::json(&ScatterResponse::failed(
+            req.method,
+            "Method names are lowercase letters, digits and '_'".to_string(),
+            "invalid_method",
+        )));
+    }
+
+    let live: Vec<String> = state
+        .rpc_workers
+        .lock()
+        .await
+        .workers()
+        .into_iter()
+        .map(|w| w.worker_id)
+        .collect();
+    let expected = req.expected.or((!live.is_empty()).then_some(live.len()));
+    let timeout = Duration::from_millis(
+        req.timeout_ms
+            .unwrap_or(DEFAULT_SCATTER_TIMEOUT_MS)
+            .min(MAX_SCATTER_TIMEOUT_MS),
+    );
+
+    let request_data = serde_json::json!({
+        "type": req.method,
+        "params": req.params.unwrap_or_else(|| serde_json::json!({})),
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    });
+
+    let gathered = match state.rabbit.scatter_call(request_data.clone(), expected, timeout).await {
+        Ok(gathered) => gathered,
+        Err(e) => {
+            error!("RPC scatter call for {} failed: {}", req.method, e);
+            return Ok(warp::reply::json(&ScatterResponse::failed(
+                req.method,
+                format!("RPC Error: {}", e),
+                e.code(),
+            )));
+        }
+    };
+
+    {
+        let mut registry = state.rpc_workers.lock().await;
+        for reply in &gathered.replies {
+            registry.record_call(ServedCall {
+                method: req.method.clone(),
+                success: RemoteError::from_reply(reply).is_none(),
+                worker_id: reply.worker_id.clone(),
+                correlation_id: Some(reply.correlation_id.clone()),
+                latency_ms: Some(reply.latency_ms),
+                at: chrono::Utc::now().to_rfc3339(),
+            });
+        }
+    }
+
+    let responses: Vec<ScatterEntry> = gathered.replies.iter().map(ScatterEntry::from_reply).collect();
+    let missing: Vec<String> = live
+        .into_iter()
+        .filter(|id| !responses.iter().any(|r| r.worker_id.as_deref() == Some(id.as_str())))
+        .collect();
+    if !missing.is_empty() {
+        warn!("RPC scatter call {} got no reply from {:?}", gathered.correlation_id, missing);
+    }
+
+    let response = ScatterResponse {
+        success: !responses.is_empty(),
+        method: req.method,
+        correlation_id: Some(gathered.correlation_id),
+        expected,
+        received: responses.len(),
+        timed_out: gathered.timed_out,
+        elapsed_ms: gathered.elapsed_ms,
+        responses,
+        missing,
+        error: None,
+        error_code: None,
+    };
+
+    broadcast_rpc_event(&state, serde_json::json!({
+        "type": "scatter_response",
+        "request": request_data,
+        "response": response
+    }));
+
+    Ok(warp::reply::json(&response))
+}
+
+/// Broadcasts each progress part of a streaming reply as a `method_progress` event.
+async fn relay_progress(
+    state: Arc<AppState>,
+    method: String,
+    request_id: String,
+    mut parts: mpsc::UnboundedReceiver<RpcPart>,
+) {
+    while let Some(part) = parts.recv().await {
+        debug!("Relaying progress part {} of {} ({})", part.seq, method, request_id);
+        broadcast_rpc_event(&state, serde_json::json!({
+            "type": "method_progress",
+            "method": method,
+            "request_id": request_id,
+            "seq": part.seq,
+            "progress": part.payload.get("progress"),
+            "worker_id": part.worker_id,
+            "correlation_id": part.correlation_id,
+            "elapsed_ms": part.elapsed_ms
+        }));
+    }
+}
+
+/// JSON-RPC 2.0 gateway: forwards a request or batch to the RPC workers over
+/// `rpc_requests` and returns their JSON-RPC reply. Requests made up only of
+/// notifications are published without waiting and answered with 204 No Content.
+#[instrument(skip(body, state))]
+pub async fn jsonrpc(
+    body: Bytes,
+    state: Arc<AppState>,
+) -> Result<warp::reply::Response, Rejection> {
+    let request: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            warn!("Rejected JSON-RPC request that is not valid JSON: {}", e);
+            return Ok(warp::reply::json(&jsonrpc_error(
+                serde_json::Value::Null,
+                JSONRPC_PARSE_ERROR,
+                "Parse error",
+                Some(serde_json::json!({ "error": e.to_string() })),
+            )).into_response());
+        }
+    };
+
+    if !expects_reply(&request) {
+        info!("Forwarding JSON-RPC notification");
+        return match state.rabbit.jsonrpc_notify(&request).await {
+            Ok(()) => Ok(StatusCode::NO_CONTENT.into_response()),
+            Err(e) => {
+                error!("Failed to forward JSON-RPC notification: {}", e);
+                Ok(warp::reply::with_status(
+                    warp::reply::json(&jsonrpc_error(
+                        serde_json::Value::Null,
+                        JSONRPC_INTERNAL_ERROR,
+                        "Internal error",
+                        Some(serde_json::json!({ "code": e.code(), "error": e.to_string() })),
+                    )),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                ).into_response())
+            }
+        };
+    }
+
+    let method = match &request {
+        serde_json::Value::Array(batch) => format!("batch({})", batch.len()),
+        request => request["method"].as_str().unwrap_or("invalid").to_string(),
+    };
+    info!("Forwarding JSON-RPC call {}", method);
+    let (response, worker_id, correlation_id, latency_ms) = match state.rabbit.jsonrpc_call(request.clone()).await {
+        Ok(reply) => (reply.payload, reply.worker_id, Some(reply.correlation_id), Some(reply.latency_ms)),
+        Err(e) => {
+            error!("JSON-RPC call {} failed: {}", method, e);
+            let remote = remote_context(&e);
+            let code = match e {
+                RpcError::Timeout { .. } => JSONRPC_SERVER_ERROR,
+                _ => JSONRPC_INTERNAL_ERROR,
+            };
+            let error = jsonrpc_error(
+                request.get("id").cloned().unwrap_or(serde_json::Value::Null),
+                code,
+                &e.to_string(),
+                Some(serde_json::json!({ "code": e.code() })),
+            );
+            (error, remote.worker_id, remote.correlation_id, remote.latency_ms)
+        }
+    };
+
+    let failed = match &response {
+        serde_json::Value::Array(replies) => replies.iter().any(|r| r.get("error").is_some()),
+        response => response.get("error").is_some(),
+    };
+    state.rpc_workers.lock().await.record_call(ServedCall {
+        method: method.clone(),
+        success: !failed,
+        worker_id: worker_id.clone(),
+        correlation_id: correlation_id.clone(),
+        latency_ms,
+        at: chrono::Utc::now().to_rfc3339(),
+    });
+    broadcast_rpc_event(&state, serde_json::json!({
+        "type": "jsonrpc_response",
+        "request": request,
+        "response": response,
+        "worker_id": worker_id,
+        "correlation_id": correlation_id,
+        "latency_ms": latency_ms
+    }));
+
+    Ok(warp::reply::json(&response).into_response())
+}
+
+fn jsonrpc_error(
+    id: serde_json::Value,
+    code: i64,
+    message: &str,
+    data: Option<serde_json::Value>,
+) -> serde_json::Value {
+    let mut error = serde_json::json!({ "code": code, "message": message });
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    serde_json::json!({ "jsonrpc": "2.0", "error": error, "id": id })
+}
+
+/// A well-formed request without an `id`; the worker runs it but sends nothing back.
+fn is_notification(request: &serde_json::Value) -> bool {
+    request.get("id").is_none()
+        && request.get("jsonrpc").and_then(|v| v.as_str()) == Some("2.0")
+        && request.get("method").is_some_and(|m| m.is_string())
+        && request.get("params").is_none_or(|p| p.is_array() || p.is_object())
+}
+
+fn expects_reply(request: &serde_json::Value) -> bool {
+    match request {
+        serde_json::Value::Array(batch) => batch.is_empty() || !batch.iter().all(is_notification),
+        request => !is_notification(request),
+    }
+}
+
+async fn record_call(state: &Arc<AppState>, method: &str, result: &Result<RpcReply, RpcError>) {
+    let context = match result {
+        Ok(reply) => ErrorContext {
+            details: None,
+            worker_id: reply.worker_id.clone(),
+            correlation_id: Some(reply.correlation_id.clone()),
+            latency_ms: Some(reply.latency_ms),
+        },
+        Err(e) => remote_context(e),
+    };
+    state.rpc_workers.lock().await.record_call(ServedCall {
+        method: method.to_string(),
+        success: result.is_ok(),
+        worker_id: context.worker_id,
+        correlation_id: context.correlation_id,
+        latency_ms: context.latency_ms,
+        at: chrono::Utc::now().to_rfc3339(),
+    });
+}
+
+/// Lists live RPC workers and which of them answered recent calls.
+#[instrument(skip(state))]
+pub async fn list_workers(
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let registry = state.rpc_workers.lock().await;
+    let workers = registry.workers();
+    debug!("Listing {} RPC workers", workers.len());
+    Ok(warp::reply::json(&RpcWorkersResponse {
+        workers,
+        served: registry.served.clone(),
+        recent_calls: registry.recent_calls.iter().cloned().collect(),
+    }))
+}
+
+/// Tracks `rpc_worker` instances from their heartbeats on the `rpc_heartbeats` exchange
+/// and relays joins, heartbeats and departures to WebSocket clients.
+#[instrument(skip(state))]
+pub async fn start_heartbeat_listener(state: Arc<AppState>) {
+    let registry = state.rpc_workers.clone();
+    heartbeats::listen(state, RPC_HEARTBEATS, registry, |state, event| async move {
+        handle_heartbeat(&state, event).await
+    })
+    .await;
+}
+
+async fn handle_heartbeat(state: &Arc<AppState>, event: serde_json::Value) {
+    let Some(worker_id) = event["worker_id"].as_str().map(str::to_string) else {
+        warn!("Received RPC worker heartbeat without worker_id");
+        return;
+    };
+    let event_type = event["type"].as_str().unwrap_or("unknown");
+    debug!("Received {} from RPC worker {}", event_type, worker_id);
+
+    let ws_data = match event_type {
+        "leaving" => {
+            info!("RPC worker {} is leaving", worker_id);
+            state.rpc_workers.lock().await.registry().remove(&worker_id);
+            serde_json::json!({
+                "type": "rpc_worker_left",
+                "worker_id": worker_id,
+                "processed": event["processed"]
+            })
+        }
+        "joined" | "heartbeat" => {
+            let worker = RpcWorker {
+                worker_id: worker_id.clone(),
+                host: event["host"].as_str().unwrap_or("unknown").to_string(),
+                pid: event["pid"].as_u64().unwrap_or(0),
+                started_at: event["started_at"].as_str().unwrap_or_default().to_string(),
+                uptime_secs: event["uptime_secs"].as_i64().unwrap_or(0),
+                processed: event["processed"].as_u64().unwrap_or(0),
+                failed: event["failed"].as_u64().unwrap_or(0),
+                in_flight: event["in_flight"].as_u64().unwrap_or(0),
+                concurrency: event["concurrency"].as_u64().unwrap_or(0),
+                served: 0,
+                last_seen: chrono::Utc::now(),
+            };
+            if state.rpc_workers.lock().await.registry().update(worker.clone()) {
+                info!("RPC worker {} joined from host {}", worker_id, worker.host);
+                serde_json::json!({ "type": "rpc_worker_joined", "worker": worker })
+            } else {
+                serde_json::json!({ "type": "rpc_worker_heartbeat", "worker": worker })
+            }
+        }
+        _ => {
+            warn!("Unknown RPC worker event type {} from {}", event_type, worker_id);
+            return;
+        }
+    };
+
+    broadcast_rpc_event(state, ws_data);
+}
+
+fn broadcast_rpc_event(state: &Arc<AppState>, data: serde_json::Value) {
+    heartbeats::broadcast(state, RPC_HEARTBEATS.demo_type, data);
+}
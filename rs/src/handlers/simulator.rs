@@ -2,9 +2,13 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use warp::reply::Json;
 
+use crate::management::{BindingInfo, BrokerRates};
+use crate::rabbitmq::{DECLARED_EXCHANGES, DECLARED_QUEUES};
 use crate::{AppState, WebSocketMessage};
 use tracing::{info, warn, debug, instrument};
 
+const DEFAULT_STATS_INTERVAL_SECS: u64 = 5;
+
 #[derive(Debug, Deserialize)]
 pub struct SimulateRequest {
     pub demo_type: String,
@@ -22,6 +26,14 @@ pub struct SimulateResponse {
 pub struct QueueStatsResponse {
     pub queues: Vec<QueueInfo>,
     pub exchanges: Vec<ExchangeInfo>,
+    pub game_subscriber_count: usize,
+    /// Broker-wide rates from the management API, when it is reachable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rates: Option<BrokerRates>,
+    /// Bindings from the management API, for drawing the topology.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bindings: Option<Vec<BindingInfo>>,
+    pub collected_at: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -30,6 +42,14 @@ pub struct QueueInfo {
     pub message_count: u32,
     pub consumer_count: u32,
     pub queue_type: String,
+    /// Messages per second, from the management API when it is reachable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publish_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deliver_rate: Option<f64>,
+    /// Why the queue could not be inspected, e.g. because it does not exist.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -37,6 +57,9 @@ pub struct ExchangeInfo {
     pub name: String,
     pub exchange_type: String,
     pub durable: bool,
+    pub exists: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[instrument(skip(request, state), fields(demo_type = %request.demo_type, flow_id))]
@@ -97,43 +120,125 @@ pub async fn simulate_message(
     Ok(warp::reply::json(&response))
 }
 
-pub async fn get_queue_stats(_state: Arc<AppState>) -> Result<Json, warp::Rejection> {
-    let queues = vec![
-        QueueInfo {
-            name: "messages".to_string(),
-            message_count: 0,
-            consumer_count: 1,
-            queue_type: "classic".to_string(),
-        },
-        QueueInfo {
-            name: "work_queue".to_string(),
-            message_count: 0,
-            consumer_count: 3,
-            queue_type: "classic".to_string(),
-        },
-        QueueInfo {
-            name: "rpc_queue".to_string(),
-            message_count: 0,
-            consumer_count: 1,
-            queue_type: "classic".to_string(),
-        },
-    ];
+#[instrument(skip(state))]
+pub async fn get_queue_stats(state: Arc<AppState>) -> Result<Json, warp::Rejection> {
+    let response = collect_queue_stats(&state).await;
+    Ok(warp::reply::json(&response))
+}
 
-    let exchanges = vec![
-        ExchangeInfo {
-            name: "game_fanout".to_string(),
-            exchange_type: "fanout".to_string(),
-            durable: true,
-        },
-        ExchangeInfo {
-            name: "amq.direct".to_string(),
-            exchange_type: "direct".to_string(),
-            durable: true,
-        },
-    ];
+/// Live counts for the app's queues and game subscriber queues, and which of its
+/// exchanges exist, all read from the broker with passive declares. Rates and bindings
+/// are added from the management API when it answers.
+async fn collect_queue_stats(state: &Arc<AppState>) -> QueueStatsResponse {
+    // One exclusive queue per WebSocket client subscribed to game events
+    let subscriber_queues: Vec<String> = state
+        .game_subscribers
+        .lock()
+        .await
+        .values()
+        .map(|subscriber| subscriber.queue.clone())
+        .collect();
+    let game_subscriber_count = subscriber_queues.len();
 
-    let response = QueueStatsResponse { queues, exchanges };
-    Ok(warp::reply::json(&response))
+    let names: Vec<String> = DECLARED_QUEUES
+        .iter()
+        .map(|q| q.to_string())
+        .chain(subscriber_queues)
+        .collect();
+    let (depths, rates, queue_rates, bindings) = tokio::join!(
+        state.rabbit.inspect_queues(&names),
+        state.management.rates(),
+        state.management.queues(),
+        state.management.bindings(),
+    );
+    if let Err(e) = &rates {
+        debug!("Queue stats without management API data: {}", e);
+    }
+    let queue_rates = queue_rates.unwrap_or_default();
+
+    let queues: Vec<QueueInfo> = depths
+        .into_iter()
+        .map(|(name, depth)| {
+            let queue_type = if DECLARED_QUEUES.contains(&name.as_str()) {
+                "classic"
+            } else {
+                "exclusive"
+            };
+            let (message_count, consumer_count, error) = match depth {
+                Ok(depth) => (depth.message_count, depth.consumer_count, None),
+                Err(e) => (0, 0, Some(e)),
+            };
+            let rates = queue_rates.iter().find(|q| q.name == name);
+            QueueInfo {
+                publish_rate: rates.map(|q| q.publish_rate),
+                deliver_rate: rates.map(|q| q.deliver_rate),
+                name,
+                message_count,
+                consumer_count,
+                queue_type: queue_type.to_string(),
+                error,
+            }
+        })
+        .collect();
+
+    let exchanges: Vec<ExchangeInfo> = state
+        .rabbit
+        .inspect_exchanges(DECLARED_EXCHANGES)
+        .await
+        .into_iter()
+        .zip(DECLARED_EXCHANGES)
+        .map(|((name, exists), (_, exchange_type))| ExchangeInfo {
+            name,
+            exchange_type: exchange_type.to_string(),
+            // Everything is declared with the default, non-durable options
+            durable: false,
+            exists: exists.is_ok(),
+            error: exists.err(),
+        })
+        .collect();
+
+    debug!("Collected stats for {} queues and {} exchanges", queues.len(), exchanges.len());
+    QueueStatsResponse {
+        queues,
+        exchanges,
+        game_subscriber_count,
+        rates: rates.ok(),
+        bindings: bindings.ok(),
+        collected_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+/// Pushes queue stats to WebSocket clients every `SIMULATOR_STATS_INTERVAL_SECS` (default
+/// 5) seconds, skipping the broker round trips while nobody is connected.
+#[instrument(skip(state))]
+pub async fn start_stats_broadcaster(state: Arc<AppState>) {
+    let interval_secs = std::env::var("SIMULATOR_STATS_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|s| *s > 0)
+        .unwrap_or(DEFAULT_STATS_INTERVAL_SECS);
+    info!("Pushing queue stats every {}s", interval_secs);
+
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(interval_secs));
+    loop {
+        interval.tick().await;
+        if state.broadcast_tx.receiver_count() == 0 {
+            continue;
+        }
+
+        let stats = collect_queue_stats(&state).await;
+        let mut data = serde_json::json!({ "action": "queue_stats" });
+        if let (Some(data), Ok(serde_json::Value::Object(stats))) = (data.as_object_mut(), serde_json::to_value(&stats)) {
+            data.extend(stats);
+        }
+        let ws_msg = WebSocketMessage {
+            demo_type: "simulator".to_string(),
+            data,
+        };
+        if state.broadcast_tx.send(ws_msg).is_err() {
+            debug!("No WebSocket clients for queue stats");
+        }
+    }
 }
 
 async fn simulate_logger_flow(
@@ -1,9 +1,17 @@
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use warp::{Reply, Rejection};
 use serde::{Deserialize, Serialize};
-use tokio::time::{sleep, Duration};
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration, Instant};
+use futures_util::future::join_all;
 use futures_util::StreamExt;
-use crate::{AppState, WebSocketMessage};
+use lapin::message::Delivery;
+use lapin::options::{BasicAckOptions, BasicCancelOptions, BasicNackOptions};
+use crate::heartbeats::{self, HeartbeatSource, Heartbeating};
+use crate::{dedup, metrics, AppState, WebSocketMessage};
 use tracing::{info, error, warn, debug, instrument};
 
 #[derive(Deserialize)]
@@ -15,8 +23,138 @@ pub struct NumberRequest {
 pub struct NumberResponse {
     pub success: bool,
     pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct CancelRequest {
+    pub task_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct ScaleRequest {
+    pub count: usize,
+}
+
+#[derive(Serialize)]
+pub struct ScaleResponse {
+    pub success: bool,
+    pub workers: usize,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RedeliveryDemoRequest {
+    pub enabled: bool,
+}
+
+#[derive(Serialize)]
+pub struct CancelResponse {
+    pub success: bool,
+    pub task_id: String,
+    pub message: String,
+}
+
+const CANCELLATION_RETENTION: Duration = Duration::from_secs(3600);
+
+/// Cancellations requested for tasks handled by the in-process workers. Queued tasks are
+/// remembered until a worker dequeues them; in-flight tasks are signalled immediately.
+/// Tasks reported finished by any worker are remembered for the same retention period so
+/// a late cancel can be refused instead of lingering in the list.
+#[derive(Debug, Default)]
+pub struct TaskCancellations {
+    cancelled: HashMap<String, Instant>,
+    in_flight: HashMap<String, oneshot::Sender<()>>,
+    finished: HashMap<String, Instant>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CancelOutcome {
+    /// The task was running on an in-process worker, which has been signalled.
+    Aborting,
+    /// The task has not been seen finishing; it will be skipped when dequeued.
+    Pending,
+    /// A worker already reported the task as done, failed or cancelled.
+    AlreadyFinished,
+}
+
+impl TaskCancellations {
+    pub fn cancel(&mut self, task_id: &str) -> CancelOutcome {
+        if let Some(abort_tx) = self.in_flight.remove(task_id) {
+            if abort_tx.send(()).is_ok() {
+                return CancelOutcome::Aborting;
+            }
+        }
+        self.finished.retain(|_, at| at.elapsed() < CANCELLATION_RETENTION);
+        if self.finished.contains_key(task_id) {
+            return CancelOutcome::AlreadyFinished;
+        }
+        self.cancelled.retain(|_, at| at.elapsed() < CANCELLATION_RETENTION);
+        self.cancelled.insert(task_id.to_string(), Instant::now());
+        CancelOutcome::Pending
+    }
+
+    /// Records that a worker finished with the task, whatever the outcome.
+    pub fn mark_finished(&mut self, task_id: &str) {
+        self.cancelled.remove(task_id);
+        self.finished.retain(|_, at| at.elapsed() < CANCELLATION_RETENTION);
+        self.finished.insert(task_id.to_string(), Instant::now());
+    }
+
+    pub fn take_cancelled(&mut self, task_id: &str) -> bool {
+        self.cancelled.remove(task_id).is_some()
+    }
+
+    pub fn begin(&mut self, task_id: &str) -> oneshot::Receiver<()> {
+        let (abort_tx, abort_rx) = oneshot::channel();
+        self.in_flight.insert(task_id.to_string(), abort_tx);
+        abort_rx
+    }
+
+    pub fn finish(&mut self, task_id: &str) {
+        self.in_flight.remove(task_id);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteWorker {
+    pub worker_id: String,
+    pub host: String,
+    pub pid: u64,
+    pub started_at: String,
+    pub processed: u64,
+    pub in_flight: u64,
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+}
+
+impl Heartbeating for RemoteWorker {
+    fn worker_id(&self) -> &str {
+        &self.worker_id
+    }
+
+    fn last_seen(&self) -> chrono::DateTime<chrono::Utc> {
+        self.last_seen
+    }
+}
+
+#[derive(Serialize)]
+pub struct WorkersListResponse {
+    pub workers: Vec<RemoteWorker>,
+}
+
+const TASK_QUEUE: &str = "number_doubler";
+const DEFAULT_WORKERS: usize = 3;
+const MAX_WORKERS: usize = 10;
+const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+const WORKER_EVENTS: HeartbeatSource = HeartbeatSource {
+    exchange: "worker_events",
+    label: "worker",
+    demo_type: "workers",
+    event_prefix: "worker",
+    timeout: Duration::from_secs(6),
+};
+const METRICS_PUSH_INTERVAL: Duration = Duration::from_secs(5);
+
 #[instrument(skip(req, state), fields(number = req.number))]
 pub async fn submit_number(
     req: NumberRequest,
@@ -27,10 +165,11 @@ pub async fn submit_number(
     
     let task_data = serde_json::json!({
         "number": req.number,
-        "task_id": task_id
+        "task_id": task_id,
+        "published_at": chrono::Utc::now().timestamp_millis()
     });
 
-    match state.rabbit.publish_message("number_doubler", &task_data.to_string()).await {
+    match state.rabbit.publish_message(TASK_QUEUE, &task_data.to_string()).await {
         Ok(_) => {
             info!("Number {} queued successfully with task_id: {}", req.number, task_id);
             tokio::spawn(start_workers(state.clone()));
@@ -39,6 +178,7 @@ pub async fn submit_number(
             Ok(warp::reply::json(&NumberResponse {
                 success: true,
                 message: "Number submitted for processing".to_string(),
+                task_id: Some(task_id),
             }))
         }
         Err(e) => {
@@ -46,99 +186,576 @@ pub async fn submit_number(
             Ok(warp::reply::json(&NumberResponse {
                 success: false,
                 message: format!("Failed to submit number: {}", e),
+                task_id: None,
             }))
         }
     }
 }
 
+#[instrument(skip(req, state), fields(task_id = %req.task_id))]
+pub async fn cancel_task(
+    req: CancelRequest,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    info!("Cancellation requested for task {}", req.task_id);
+
+    let outcome = state.task_cancellations.lock().await.cancel(&req.task_id);
+    match outcome {
+        CancelOutcome::Aborting => info!("Signalled in-process worker to abort task {}", req.task_id),
+        CancelOutcome::AlreadyFinished => {
+            info!("Task {} already finished, nothing to cancel", req.task_id);
+            return Ok(warp::reply::json(&CancelResponse {
+                success: false,
+                task_id: req.task_id,
+                message: "Task has already finished".to_string(),
+            }));
+        }
+        CancelOutcome::Pending => {}
+    }
+
+    // Standalone workers keep their own cancellation lists, fed by the worker_control exchange
+    let control_msg = serde_json::json!({
+        "type": "cancel",
+        "task_id": req.task_id
+    });
+    if let Err(e) = state.rabbit.publish_fanout("worker_control", &control_msg.to_string()).await {
+        error!("Failed to publish cancellation for task {}: {}", req.task_id, e);
+        return Ok(warp::reply::json(&CancelResponse {
+            success: false,
+            task_id: req.task_id,
+            message: format!("Failed to cancel task: {}", e),
+        }));
+    }
+
+    Ok(warp::reply::json(&CancelResponse {
+        success: true,
+        message: if outcome == CancelOutcome::Aborting {
+            "Task is being aborted".to_string()
+        } else {
+            "Task will be skipped when dequeued, or aborted if a worker is running it".to_string()
+        },
+        task_id: req.task_id,
+    }))
+}
+
+#[instrument(skip(req, state), fields(enabled = req.enabled))]
+pub async fn set_redelivery_demo(
+    req: RedeliveryDemoRequest,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    info!("Setting forced redelivery demo to {}", req.enabled);
+    state.redelivery_demo.store(req.enabled, Ordering::SeqCst);
+
+    let control_msg = serde_json::json!({
+        "type": "redelivery_demo",
+        "enabled": req.enabled
+    });
+    if let Err(e) = state.rabbit.publish_fanout("worker_control", &control_msg.to_string()).await {
+        warn!("Failed to forward redelivery demo setting to standalone workers: {}", e);
+    }
+
+    Ok(warp::reply::json(&req))
+}
+
+/// In-process workers currently running, each with its own stop signal so the pool can
+/// be scaled down or drained one worker at a time.
+#[derive(Debug, Default)]
+pub struct WorkerPool {
+    workers: HashMap<u8, WorkerHandle>,
+    started: bool,
+}
+
+#[derive(Debug)]
+struct WorkerHandle {
+    stop_tx: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl WorkerPool {
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+}
+
+enum TaskOutcome {
+    Done,
+    Requeue,
+}
+
+#[instrument(skip(req, state), fields(count = req.count))]
+pub async fn scale_workers(
+    req: ScaleRequest,
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let count = req.count.min(MAX_WORKERS);
+    info!("Scaling in-process workers to {}", count);
+    scale_pool(&state, count).await;
+
+    let workers = state.worker_pool.lock().await.size();
+    broadcast_workers_event(&state, serde_json::json!({
+        "type": "pool_scaled",
+        "workers": workers
+    }));
+    Ok(warp::reply::json(&ScaleResponse {
+        success: true,
+        workers,
+    }))
+}
+
 #[instrument(skip(state))]
 async fn start_workers(state: Arc<AppState>) {
-    info!("Starting worker processes");
-    for worker_id in 1..=3 {
-        let state_clone = state.clone();
-        tokio::spawn(async move {
-            info!("Starting worker {}", worker_id);
-            worker_process(worker_id, state_clone).await;
-        });
+    if state.worker_pool.lock().await.started {
+        debug!("Worker pool already started");
+        return;
     }
+    info!("Starting worker processes");
+    scale_pool(&state, DEFAULT_WORKERS).await;
     info!("All worker processes started");
 }
 
-#[instrument(skip(state), fields(worker_id = worker_id))]
-async fn worker_process(worker_id: u8, state: Arc<AppState>) {
+async fn scale_pool(state: &Arc<AppState>, count: usize) {
+    let stopping: Vec<(u8, WorkerHandle)> = {
+        let mut pool = state.worker_pool.lock().await;
+        pool.started = true;
+        let mut worker_id: u8 = 1;
+        while pool.workers.len() < count {
+            if let std::collections::hash_map::Entry::Vacant(slot) = pool.workers.entry(worker_id) {
+                info!("Starting worker {}", worker_id);
+                let (stop_tx, stop_rx) = watch::channel(false);
+                let task = tokio::spawn(worker_process(worker_id, state.clone(), stop_rx));
+                slot.insert(WorkerHandle { stop_tx, task });
+            }
+            worker_id += 1;
+        }
+
+        let mut ids: Vec<u8> = pool.workers.keys().copied().collect();
+        ids.sort_unstable();
+        ids.into_iter()
+            .skip(count)
+            .filter_map(|id| pool.workers.remove(&id).map(|handle| (id, handle)))
+            .collect()
+    };
+
+    // Signal every stopping worker first so their grace periods run concurrently
+    for (worker_id, handle) in &stopping {
+        info!("Stopping worker {}", worker_id);
+        let _ = handle.stop_tx.send(true);
+    }
+    let results = join_all(stopping.into_iter().map(|(worker_id, handle)| async move {
+        (worker_id, handle.task.await)
+    }))
+    .await;
+    for (worker_id, result) in results {
+        if let Err(e) = result {
+            error!("Worker {} ended abnormally: {}", worker_id, e);
+        }
+    }
+}
+
+/// Stops every in-process worker, letting in-flight tasks finish within the grace period.
+#[instrument(skip(state))]
+pub async fn shutdown_workers(state: Arc<AppState>) {
+    info!("Draining in-process workers");
+    scale_pool(&state, 0).await;
+    info!("All in-process workers stopped");
+}
+
+fn shutdown_grace() -> Duration {
+    std::env::var("WORKER_SHUTDOWN_GRACE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_SHUTDOWN_GRACE)
+}
+
+/// Resolves once a stop has been requested and the grace period has elapsed.
+async fn grace_expired(mut stop_rx: watch::Receiver<bool>, grace: Duration) {
+    while !*stop_rx.borrow() {
+        if stop_rx.changed().await.is_err() {
+            break;
+        }
+    }
+    sleep(grace).await;
+}
+
+#[instrument(skip(state, stop_rx), fields(worker_id = worker_id))]
+async fn worker_process(worker_id: u8, state: Arc<AppState>, mut stop_rx: watch::Receiver<bool>) {
     info!("Worker {} starting processing loop", worker_id);
-    
-    match state.rabbit.consume_queue("number_doubler").await {
-        Ok(consumer) => {
-            info!("Worker {} connected to queue consumer", worker_id);
-            let mut stream = consumer;
-            
-            while let Some(delivery_result) = stream.next().await {
+
+    let (channel, mut stream) = match state.rabbit.consume_queue_with_prefetch(TASK_QUEUE, 1).await {
+        Ok(consumer) => consumer,
+        Err(e) => {
+            error!("Worker {} failed to connect to queue consumer: {}", worker_id, e);
+            return;
+        }
+    };
+    info!("Worker {} connected to queue consumer", worker_id);
+
+    loop {
+        tokio::select! {
+            biased;
+            _ = stop_rx.changed() => {
+                info!("Worker {} received stop signal", worker_id);
+                break;
+            }
+            delivery_result = stream.next() => {
                 match delivery_result {
-                    Ok(delivery) => {
-                        debug!("Worker {} received message", worker_id);
-                        
-                        match String::from_utf8(delivery.data.clone()) {
-                            Ok(task_str) => {
-                                match serde_json::from_str::<serde_json::Value>(&task_str) {
-                                    Ok(task_data) => {
-                                        if let Some(number) = task_data["number"].as_i64() {
-                                            let task_id = task_data["task_id"].as_str().unwrap_or("unknown");
-                                            info!("Worker {} processing task {} with number {}", worker_id, task_id, number);
-                                            
-                                            let delay = rand::random::<u64>() % 3000 + 1000;
-                                            debug!("Worker {} simulating processing delay of {}ms", worker_id, delay);
-                                            sleep(Duration::from_millis(delay)).await;
-                                            
-                                            let result = number * 2;
-                                            info!("Worker {} completed task {} ({}*2={})", worker_id, task_id, number, result);
-                                            
-                                            let ws_msg = WebSocketMessage {
-                                                demo_type: "workers".to_string(),
-                                                data: serde_json::json!({
-                                                    "worker_id": worker_id,
-                                                    "task_id": task_id,
-                                                    "original": number,
-                                                    "result": result,
-                                                    "processing_time": delay
-                                                }),
-                                            };
-                                            
-                                            if let Err(_) = state.broadcast_tx.send(ws_msg) {
-                                                warn!("Worker {} could not broadcast result - no WebSocket clients", worker_id);
-                                            } else {
-                                                debug!("Worker {} broadcasted result to WebSocket clients", worker_id);
-                                            }
-                                        } else {
-                                            warn!("Worker {} received task without valid number field", worker_id);
-                                        }
-                                    }
-                                    Err(e) => {
-                                        error!("Worker {} failed to parse task JSON: {}", worker_id, e);
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                error!("Worker {} received invalid UTF-8 message: {}", worker_id, e);
-                            }
-                        }
-                        
-                        if let Err(e) = delivery.ack(lapin::options::BasicAckOptions::default()).await {
-                            error!("Worker {} failed to ack message: {}", worker_id, e);
-                        } else {
-                            debug!("Worker {} acknowledged message", worker_id);
+                    Some(Ok(delivery)) => {
+                        handle_delivery(worker_id, &state, delivery, stop_rx.clone()).await;
+                        if *stop_rx.borrow() {
+                            info!("Worker {} received stop signal", worker_id);
+                            break;
                         }
                     }
-                    Err(e) => {
+                    Some(Err(e)) => {
                         error!("Worker {} delivery error: {}", worker_id, e);
                         break;
                     }
+                    None => break,
                 }
             }
-            warn!("Worker {} processing loop ended", worker_id);
         }
+    }
+
+    // Stop taking new deliveries, then hand back anything the broker already pushed to us
+    let consumer_tag = stream.tag();
+    if let Err(e) = channel.basic_cancel(consumer_tag.as_str(), BasicCancelOptions::default()).await {
+        warn!("Worker {} failed to cancel consumer: {}", worker_id, e);
+    } else {
+        debug!("Worker {} cancelled consumer {}", worker_id, consumer_tag);
+    }
+    while let Ok(Some(Ok(delivery))) = tokio::time::timeout(Duration::from_millis(100), stream.next()).await {
+        if let Err(e) = delivery.nack(BasicNackOptions { requeue: true, ..Default::default() }).await {
+            error!("Worker {} failed to requeue buffered message: {}", worker_id, e);
+        }
+    }
+    if let Err(e) = channel.close(200, "worker stopped").await {
+        warn!("Worker {} failed to close channel: {}", worker_id, e);
+    }
+    warn!("Worker {} processing loop ended", worker_id);
+}
+
+async fn handle_delivery(
+    worker_id: u8,
+    state: &Arc<AppState>,
+    delivery: Delivery,
+    stop_rx: watch::Receiver<bool>,
+) {
+    debug!("Worker {} received message", worker_id);
+
+    let message_id = dedup::message_id(&delivery);
+    if let Some(message_id) = &message_id {
+        if state.worker_dedup.is_duplicate(message_id).await {
+            info!("Worker {} suppressing duplicate delivery of message {}", worker_id, message_id);
+            let task_id = serde_json::from_slice::<serde_json::Value>(&delivery.data)
+                .ok()
+                .and_then(|task| task["task_id"].as_str().map(str::to_string));
+            broadcast_workers_event(state, serde_json::json!({
+                "type": "duplicate_suppressed",
+                "worker_id": worker_id,
+                "task_id": task_id,
+                "message_id": message_id,
+                "redelivered": delivery.redelivered
+            }));
+            if let Err(e) = delivery.ack(BasicAckOptions::default()).await {
+                error!("Worker {} failed to ack duplicate message: {}", worker_id, e);
+            }
+            return;
+        }
+    }
+
+    let metrics_id = worker_id.to_string();
+    if delivery.redelivered {
+        state.worker_metrics.lock().await.record_retried(&metrics_id, TASK_QUEUE);
+    }
+
+    let outcome = process_task(worker_id, state, &delivery.data, stop_rx).await;
+
+    if let TaskOutcome::Requeue = outcome {
+        info!("Worker {} returning unfinished message {:?} to the queue", worker_id, message_id);
+        if let Err(e) = delivery.nack(BasicNackOptions { requeue: true, ..Default::default() }).await {
+            error!("Worker {} failed to requeue message: {}", worker_id, e);
+        }
+        return;
+    }
+
+    if let Some(message_id) = &message_id {
+        state.worker_dedup.mark_processed(message_id).await;
+    }
+
+    // Demo mode: pretend the worker died before acking so the broker redelivers
+    if state.redelivery_demo.load(Ordering::SeqCst) && !delivery.redelivered {
+        info!("Worker {} forcing redelivery of message {:?}", worker_id, message_id);
+        broadcast_workers_event(state, serde_json::json!({
+            "type": "redelivery_forced",
+            "worker_id": worker_id,
+            "message_id": message_id
+        }));
+        if let Err(e) = delivery.nack(BasicNackOptions { requeue: true, ..Default::default() }).await {
+            error!("Worker {} failed to nack message: {}", worker_id, e);
+        }
+    } else if let Err(e) = delivery.ack(BasicAckOptions::default()).await {
+        error!("Worker {} failed to ack message: {}", worker_id, e);
+    } else {
+        debug!("Worker {} acknowledged message", worker_id);
+    }
+}
+
+async fn process_task(
+    worker_id: u8,
+    state: &Arc<AppState>,
+    data: &[u8],
+    stop_rx: watch::Receiver<bool>,
+) -> TaskOutcome {
+    let metrics_id = worker_id.to_string();
+    let task_data = match serde_json::from_slice::<serde_json::Value>(data) {
+        Ok(task_data) => task_data,
         Err(e) => {
-            error!("Worker {} failed to connect to queue consumer: {}", worker_id, e);
+            error!("Worker {} failed to parse task JSON: {}", worker_id, e);
+            state.worker_metrics.lock().await.record_failed(&metrics_id, TASK_QUEUE);
+            return TaskOutcome::Done;
+        }
+    };
+    let Some(number) = task_data["number"].as_i64() else {
+        warn!("Worker {} received task without valid number field", worker_id);
+        state.worker_metrics.lock().await.record_failed(&metrics_id, TASK_QUEUE);
+        return TaskOutcome::Done;
+    };
+    let task_id = task_data["task_id"].as_str().unwrap_or("unknown");
+
+    if state.task_cancellations.lock().await.take_cancelled(task_id) {
+        info!("Worker {} skipping cancelled task {}", worker_id, task_id);
+        state.task_cancellations.lock().await.mark_finished(task_id);
+        broadcast_cancelled(state, task_id, "queued", &worker_id.to_string());
+        return TaskOutcome::Done;
+    }
+
+    info!("Worker {} processing task {} with number {}", worker_id, task_id, number);
+    let queue_wait = metrics::queue_wait_ms(task_data["published_at"].as_i64());
+    let started = Instant::now();
+    let cancel_rx = state.task_cancellations.lock().await.begin(task_id);
+
+    let delay = rand::random::<u64>() % 3000 + 1000;
+    debug!("Worker {} simulating processing delay of {}ms", worker_id, delay);
+    let interrupted = tokio::select! {
+        _ = sleep(Duration::from_millis(delay)) => None,
+        _ = cancel_rx => Some(TaskOutcome::Done),
+        _ = grace_expired(stop_rx, shutdown_grace()) => Some(TaskOutcome::Requeue),
+    };
+    {
+        let mut cancellations = state.task_cancellations.lock().await;
+        cancellations.finish(task_id);
+        if !matches!(interrupted, Some(TaskOutcome::Requeue)) {
+            cancellations.mark_finished(task_id);
         }
     }
-}
\ No newline at end of file
+
+    match interrupted {
+        Some(TaskOutcome::Done) => {
+            info!("Worker {} aborted cancelled task {}", worker_id, task_id);
+            broadcast_cancelled(state, task_id, "in_flight", &worker_id.to_string());
+            TaskOutcome::Done
+        }
+        Some(TaskOutcome::Requeue) => {
+            warn!("Worker {} could not finish task {} within the shutdown grace period", worker_id, task_id);
+            TaskOutcome::Requeue
+        }
+        None => {
+            let result = number * 2;
+            let processing_ms = started.elapsed().as_millis() as u64;
+            info!("Worker {} completed task {} ({}*2={}) in {}ms after waiting {:?}ms in queue",
+                worker_id, task_id, number, result, processing_ms, queue_wait);
+            state.worker_metrics.lock().await
+                .record_processed(&metrics_id, TASK_QUEUE, queue_wait, processing_ms);
+
+            let ws_msg = WebSocketMessage {
+                demo_type: "workers".to_string(),
+                data: serde_json::json!({
+                    "type": "result",
+                    "worker_id": worker_id,
+                    "task_id": task_id,
+                    "original": number,
+                    "result": result,
+                    "processing_time": delay,
+                    "queue_wait": queue_wait
+                }),
+            };
+
+            if state.broadcast_tx.send(ws_msg).is_err() {
+                warn!("Worker {} could not broadcast result - no WebSocket clients", worker_id);
+            } else {
+                debug!("Worker {} broadcasted result to WebSocket clients", worker_id);
+            }
+            TaskOutcome::Done
+        }
+    }
+}
+
+#[instrument(skip(state))]
+pub async fn get_metrics(
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    debug!("Getting worker metrics");
+    let metrics = state.worker_metrics.lock().await.clone();
+    Ok(warp::reply::json(&metrics))
+}
+
+/// Pushes a metrics snapshot to WebSocket clients so the UI can chart load distribution.
+#[instrument(skip(state))]
+pub async fn start_metrics_broadcaster(state: Arc<AppState>) {
+    info!("Broadcasting worker metrics every {:?}", METRICS_PUSH_INTERVAL);
+    let mut interval = tokio::time::interval(METRICS_PUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let metrics = state.worker_metrics.lock().await.clone();
+        if metrics.workers.is_empty() {
+            continue;
+        }
+        broadcast_workers_event(&state, serde_json::json!({
+            "type": "metrics",
+            "metrics": metrics
+        }));
+    }
+}
+
+#[instrument(skip(state))]
+pub async fn list_workers(
+    state: Arc<AppState>,
+) -> Result<impl Reply, Rejection> {
+    let workers = state.remote_workers.lock().await.workers();
+    debug!("Listing {} remote workers", workers.len());
+    Ok(warp::reply::json(&WorkersListResponse { workers }))
+}
+
+/// Relays events published by standalone `worker` processes on the `worker_events`
+/// exchange and tracks which of them are alive based on their heartbeats.
+#[instrument(skip(state))]
+pub async fn start_worker_event_listener(state: Arc<AppState>) {
+    let registry = state.remote_workers.clone();
+    heartbeats::listen(state, WORKER_EVENTS, registry, |state, event| async move {
+        handle_worker_event(&state, event).await
+    })
+    .await;
+}
+
+async fn handle_worker_event(state: &Arc<AppState>, event: serde_json::Value) {
+    let Some(worker_id) = event["worker_id"].as_str().map(str::to_string) else {
+        warn!("Received worker event without worker_id");
+        return;
+    };
+    let event_type = event["type"].as_str().unwrap_or("unknown");
+    debug!("Received {} event from worker {}", event_type, worker_id);
+
+    let ws_data = match event_type {
+        "leaving" => {
+            info!("Worker {} is leaving", worker_id);
+            state.remote_workers.lock().await.remove(&worker_id);
+            serde_json::json!({
+                "type": "worker_left",
+                "worker_id": worker_id,
+                "processed": event["processed"]
+            })
+        }
+        "joined" | "heartbeat" | "result" | "failed" | "cancelled" | "duplicate_suppressed" | "redelivery_forced" => {
+            let worker = RemoteWorker {
+                worker_id: worker_id.clone(),
+                host: event["host"].as_str().unwrap_or("unknown").to_string(),
+                pid: event["pid"].as_u64().unwrap_or(0),
+                started_at: event["started_at"].as_str().unwrap_or_default().to_string(),
+                processed: event["processed"].as_u64().unwrap_or(0),
+                in_flight: event["in_flight"].as_u64().unwrap_or(0),
+                last_seen: chrono::Utc::now(),
+            };
+            let is_new = state.remote_workers.lock().await.update(worker.clone());
+
+            if is_new {
+                info!("Worker {} joined from host {}", worker_id, worker.host);
+                broadcast_workers_event(state, serde_json::json!({
+                    "type": "worker_joined",
+                    "worker": worker
+                }));
+            }
+
+            if matches!(event_type, "result" | "failed" | "cancelled") {
+                if let Some(task_id) = event["task_id"].as_str() {
+                    state.task_cancellations.lock().await.mark_finished(task_id);
+                }
+            }
+
+            if event["redelivered"].as_bool() == Some(true) && matches!(event_type, "result" | "failed") {
+                state.worker_metrics.lock().await.record_retried(&worker_id, TASK_QUEUE);
+            }
+
+            match event_type {
+                "failed" => {
+                    state.worker_metrics.lock().await.record_failed(&worker_id, TASK_QUEUE);
+                    serde_json::json!({
+                        "type": "failed",
+                        "worker_id": worker_id,
+                        "error": event["error"]
+                    })
+                }
+                "result" => {
+                    state.worker_metrics.lock().await.record_processed(
+                        &worker_id,
+                        TASK_QUEUE,
+                        event["queue_wait"].as_u64(),
+                        event["processing_time"].as_u64().unwrap_or(0),
+                    );
+                    serde_json::json!({
+                        "type": "result",
+                        "worker_id": worker_id,
+                        "host": worker.host,
+                        "task_id": event["task_id"],
+                        "original": event["original"],
+                        "result": event["result"],
+                        "processing_time": event["processing_time"],
+                        "queue_wait": event["queue_wait"]
+                    })
+                }
+                "heartbeat" => serde_json::json!({
+                    "type": "worker_heartbeat",
+                    "worker": worker
+                }),
+                "cancelled" => serde_json::json!({
+                    "type": "cancelled",
+                    "worker_id": worker_id,
+                    "task_id": event["task_id"],
+                    "stage": event["stage"]
+                }),
+                "redelivery_forced" => serde_json::json!({
+                    "type": "redelivery_forced",
+                    "worker_id": worker_id,
+                    "message_id": event["message_id"]
+                }),
+                "duplicate_suppressed" => serde_json::json!({
+                    "type": "duplicate_suppressed",
+                    "worker_id": worker_id,
+                    "task_id": event["task_id"],
+                    "message_id": event["message_id"],
+                    "redelivered": event["redelivered"]
+                }),
+                _ => return,
+            }
+        }
+        _ => {
+            warn!("Unknown worker event type {} from worker {}", event_type, worker_id);
+            return;
+        }
+    };
+
+    broadcast_workers_event(state, ws_data);
+}
+
+fn broadcast_cancelled(state: &Arc<AppState>, task_id: &str, stage: &str, worker_id: &str) {
+    broadcast_workers_event(state, serde_json::json!({
+        "type": "cancelled",
+        "worker_id": worker_id,
+        "task_id": task_id,
+        "stage": stage
+    }));
+}
+
+fn broadcast_workers_event(state: &Arc<AppState>, data: serde_json::Value) {
+    heartbeats::broadcast(state, WORKER_EVENTS.demo_type, data);
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
+use crate::{AppState, WebSocketMessage};
+use tracing::{info, error, warn, debug};
+
+/// How often registries are checked for workers that stopped sending heartbeats.
+const REAP_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A worker process tracked through the heartbeats it publishes.
+pub trait Heartbeating: Clone {
+    fn worker_id(&self) -> &str;
+    fn last_seen(&self) -> DateTime<Utc>;
+}
+
+/// Live workers by id, as last reported by their heartbeats.
+#[derive(Debug)]
+pub struct WorkerRegistry<W> {
+    workers: HashMap<String, W>,
+}
+
+impl<W> Default for WorkerRegistry<W> {
+    fn default() -> Self {
+        Self { workers: HashMap::new() }
+    }
+}
+
+impl<W: Heartbeating> WorkerRegistry<W> {
+    /// Stores a worker's latest heartbeat, returning whether it was not known yet.
+    pub fn update(&mut self, worker: W) -> bool {
+        self.workers.insert(worker.worker_id().to_string(), worker).is_none()
+    }
+
+    pub fn remove(&mut self, worker_id: &str) -> Option<W> {
+        self.workers.remove(worker_id)
+    }
+
+    /// Removes and returns the workers not heard from within `timeout`.
+    pub fn remove_silent(&mut self, timeout: Duration) -> Vec<W> {
+        let timeout = chrono::Duration::from_std(timeout).unwrap_or_default();
+        let now = Utc::now();
+        let lost: Vec<String> = self
+            .workers
+            .values()
+            .filter(|w| now - w.last_seen() > timeout)
+            .map(|w| w.worker_id().to_string())
+            .collect();
+        lost.iter().filter_map(|id| self.workers.remove(id)).collect()
+    }
+
+    /// All live workers, sorted by id.
+    pub fn workers(&self) -> Vec<W> {
+        let mut workers: Vec<W> = self.workers.values().cloned().collect();
+        workers.sort_by(|a, b| a.worker_id().cmp(b.worker_id()));
+        workers
+    }
+}
+
+/// State that holds a `WorkerRegistry`, so the reaper can work on it.
+pub trait HasWorkers: Send + 'static {
+    type Worker: Heartbeating + Send;
+
+    fn registry(&mut self) -> &mut WorkerRegistry<Self::Worker>;
+}
+
+impl<W: Heartbeating + Send + 'static> HasWorkers for WorkerRegistry<W> {
+    type Worker = W;
+
+    fn registry(&mut self) -> &mut WorkerRegistry<W> {
+        self
+    }
+}
+
+/// Where a kind of worker publishes its heartbeats and how it is reported.
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatSource {
+    /// Fanout exchange the workers publish their events on.
+    pub exchange: &'static str,
+    /// Name used in log lines, e.g. `RPC worker`.
+    pub label: &'static str,
+    /// `demo_type` of the WebSocket messages.
+    pub demo_type: &'static str,
+    /// Prefix of the `<prefix>_lost` event sent when a worker goes silent.
+    pub event_prefix: &'static str,
+    /// How long a worker may go without a heartbeat before it is assumed to have crashed.
+    pub timeout: Duration,
+}
+
+/// Consumes worker events from `source.exchange`, passing each to `handle`, while a
+/// reaper drops workers from `registry` once they miss their heartbeats.
+pub async fn listen<R, F, Fut>(state: Arc<AppState>, source: HeartbeatSource, registry: Arc<Mutex<R>>, handle: F)
+where
+    R: HasWorkers,
+    F: Fn(Arc<AppState>, serde_json::Value) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    tokio::spawn(reap_silent_workers(state.clone(), source, registry));
+
+    let mut consumer = match state.rabbit.subscribe_to_exchange(source.exchange).await {
+        Ok(consumer) => consumer,
+        Err(e) => {
+            error!("Failed to subscribe to {} events: {}", source.label, e);
+            return;
+        }
+    };
+
+    info!("Listening for {} events on {}", source.label, source.exchange);
+    while let Some(delivery_result) = consumer.next().await {
+        match delivery_result {
+            Ok(delivery) => match serde_json::from_slice::<serde_json::Value>(&delivery.data) {
+                Ok(event) => handle(state.clone(), event).await,
+                Err(e) => error!("Failed to parse {} event: {}", source.label, e),
+            },
+            Err(e) => {
+                error!("{} event delivery error: {}", source.label, e);
+                break;
+            }
+        }
+    }
+    warn!("{} event listener ended", source.label);
+}
+
+async fn reap_silent_workers<R: HasWorkers>(state: Arc<AppState>, source: HeartbeatSource, registry: Arc<Mutex<R>>) {
+    loop {
+        sleep(REAP_INTERVAL).await;
+
+        let lost = registry.lock().await.registry().remove_silent(source.timeout);
+        for worker in lost {
+            warn!("{} {} missed its heartbeats, assuming it crashed", source.label, worker.worker_id());
+            broadcast(&state, source.demo_type, serde_json::json!({
+                "type": format!("{}_lost", source.event_prefix),
+                "worker_id": worker.worker_id(),
+                "last_seen": worker.last_seen().to_rfc3339()
+            }));
+        }
+    }
+}
+
+pub fn broadcast(state: &AppState, demo_type: &str, data: serde_json::Value) {
+    let ws_msg = WebSocketMessage {
+        demo_type: demo_type.to_string(),
+        data,
+    };
+    if state.broadcast_tx.send(ws_msg).is_err() {
+        debug!("No WebSocket clients for {} event", demo_type);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct Worker {
+        id: String,
+        last_seen: DateTime<Utc>,
+    }
+
+    impl Heartbeating for Worker {
+        fn worker_id(&self) -> &str {
+            &self.id
+        }
+
+        fn last_seen(&self) -> DateTime<Utc> {
+            self.last_seen
+        }
+    }
+
+    fn worker(id: &str, secs_ago: i64) -> Worker {
+        Worker {
+            id: id.to_string(),
+            last_seen: Utc::now() - chrono::Duration::seconds(secs_ago),
+        }
+    }
+
+    #[test]
+    fn update_reports_new_workers_only() {
+        let mut registry = WorkerRegistry::default();
+        assert!(registry.update(worker("b", 0)));
+        assert!(registry.update(worker("a", 0)));
+        assert!(!registry.update(worker("b", 0)));
+
+        let ids: Vec<String> = registry.workers().into_iter().map(|w| w.id).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn silent_workers_are_removed_once() {
+        let mut registry = WorkerRegistry::default();
+        registry.update(worker("alive", 1));
+        registry.update(worker("silent", 10));
+
+        let lost = registry.remove_silent(Duration::from_secs(6));
+        assert_eq!(lost.len(), 1);
+        assert_eq!(lost[0].id, "silent");
+        assert!(registry.remove_silent(Duration::from_secs(6)).is_empty());
+        assert!(registry.remove("alive").is_some());
+        assert!(registry.workers().is_empty());
+    }
+}
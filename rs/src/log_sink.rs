@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::Stream;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{Duration, Instant};
+use tracing::{info, error, warn, debug, instrument};
+use warp::hyper::body::Bytes;
+
+const ACTIVE_FILE: &str = "logger.jsonl";
+const ROTATED_PREFIX: &str = "logger-";
+const ROTATED_SUFFIX: &str = ".jsonl.gz";
+/// Rotated files are renamed to this first and gzipped in the background.
+const STAGED_SUFFIX: &str = ".jsonl";
+const COMPRESSING_SUFFIX: &str = ".jsonl.gz.tmp";
+const EXPORT_CHUNK_BYTES: usize = 64 * 1024;
+const ROTATED_TIME_FORMAT: &str = "%Y%m%dT%H%M%S%3f";
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_ROTATE_SECS: u64 = 3600;
+
+/// Appends consumed logger messages to `logger.jsonl` in a directory, rotating the file
+/// once it grows past `max_bytes` or gets older than `rotate_after`. Rotated files are
+/// gzipped as `logger-<closed at>.jsonl.gz`. The lock only covers the active file handle,
+/// so compression and exports never hold up the logger consumer.
+#[derive(Debug)]
+pub struct LogFileSink {
+    dir: PathBuf,
+    max_bytes: u64,
+    rotate_after: Duration,
+    active: Mutex<Option<ActiveFile>>,
+}
+
+#[derive(Debug)]
+struct ActiveFile {
+    file: File,
+    size: u64,
+    opened_at: Instant,
+}
+
+impl LogFileSink {
+    /// Enabled by `LOGGER_SINK_DIR`; `LOGGER_SINK_MAX_BYTES` and `LOGGER_SINK_ROTATE_SECS`
+    /// tune rotation.
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("LOGGER_SINK_DIR").ok().filter(|d| !d.is_empty())?;
+        let max_bytes = std::env::var("LOGGER_SINK_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_BYTES);
+        let rotate_secs = std::env::var("LOGGER_SINK_ROTATE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_ROTATE_SECS);
+        info!("Logger file sink enabled in {} (max {} bytes, rotate every {}s)", dir, max_bytes, rotate_secs);
+        Some(Self {
+            dir: PathBuf::from(dir),
+            max_bytes,
+            rotate_after: Duration::from_secs(rotate_secs),
+            active: Mutex::new(None),
+        })
+    }
+
+    fn active_path(&self) -> PathBuf {
+        self.dir.join(ACTIVE_FILE)
+    }
+
+    #[instrument(skip(self, entry))]
+    pub async fn write(&self, entry: &serde_json::Value) -> std::io::Result<()> {
+        let mut line = entry.to_string();
+        line.push('\n');
+
+        let mut active = self.active.lock().await;
+        if active.as_ref().is_some_and(|a| self.is_due(a, line.len() as u64)) {
+            let staged = self.rotate(&mut active).await?;
+            compress_in_background(staged);
+        }
+        if active.is_none() {
+            *active = Some(self.open().await?);
+        }
+
+        let Some(current) = active.as_mut() else {
+            return Ok(());
+        };
+        current.file.write_all(line.as_bytes()).await?;
+        current.file.flush().await?;
+        current.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Rotates the active file if it has outlived the rotation interval, so quiet
+    /// periods still produce time-bounded files.
+    pub async fn rotate_if_due(&self) {
+        let mut active = self.active.lock().await;
+        if active.as_ref().is_some_and(|a| a.size > 0 && self.is_due(a, 0)) {
+            match self.rotate(&mut active).await {
+                Ok(staged) => compress_in_background(staged),
+                Err(e) => error!("Failed to rotate logger sink file: {}", e),
+            }
+        }
+    }
+
+    fn is_due(&self, active: &ActiveFile, incoming: u64) -> bool {
+        active.size + incoming > self.max_bytes || active.opened_at.elapsed() >= self.rotate_after
+    }
+
+    async fn open(&self) -> std::io::Result<ActiveFile> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.active_path();
+        let file = OpenOptions::new().create(true).append(true).open(&path).await?;
+        let size = file.metadata().await?.len();
+        debug!("Opened logger sink file {:?} ({} bytes)", path, size);
+        Ok(ActiveFile {
+            file,
+            size,
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Closes the active file and moves it aside as `logger-<closed at>.jsonl`, returning
+    /// the staged path. Only a rename happens here; compressing is left to the caller.
+    async fn rotate(&self, active: &mut Option<ActiveFile>) -> std::io::Result<PathBuf> {
+        if let Some(mut current) = active.take() {
+            current.file.flush().await?;
+        }
+
+        // Names only have millisecond precision; never overwrite an earlier rotation
+        let staged = loop {
+            let closed_at = chrono::Utc::now().format(ROTATED_TIME_FORMAT).to_string();
+            let staged = self.dir.join(format!("{}{}{}", ROTATED_PREFIX, closed_at, STAGED_SUFFIX));
+            let taken = tokio::fs::try_exists(&staged).await?
+                || compressed_path(&staged).is_some_and(|c| c.exists());
+            if !taken {
+                break staged;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        };
+        tokio::fs::rename(self.active_path(), &staged).await?;
+        debug!("Staged logger sink file {:?} for compression", staged);
+        Ok(staged)
+    }
+
+    /// Streams the JSON lines whose `timestamp` falls within `[from_ms, to_ms]`, oldest
+    /// file first, from both rotated and active files.
+    #[instrument(skip(self))]
+    pub async fn export(
+        &self,
+        from_ms: Option<i64>,
+        to_ms: Option<i64>,
+    ) -> std::io::Result<impl Stream<Item = std::io::Result<Bytes>>> {
+        // Open every file under the lock so a concurrent rotation cannot move anything
+        // between listing and reading; the reading itself happens after it is released
+        let sources = {
+            let _active = self.active.lock().await;
+            let dir = self.dir.clone();
+            let active_path = self.active_path();
+            tokio::task::spawn_blocking(move || open_export_sources(&dir, &active_path, from_ms))
+                .await
+                .map_err(std::io::Error::other)??
+        };
+        debug!("Exporting logger messages from {} files", sources.len());
+
+        let (tx, rx) = mpsc::channel(4);
+        tokio::task::spawn_blocking(move || {
+            let mut output = ChunkWriter { tx: tx.clone(), buffer: Vec::new() };
+            let result = sources
+                .into_iter()
+                .try_for_each(|source| copy_matching_lines(source, &mut output, from_ms, to_ms))
+                .and_then(|_| output.flush());
+            if let Err(e) = result {
+                if e.kind() != std::io::ErrorKind::BrokenPipe {
+                    error!("Logger export failed: {}", e);
+                    let _ = tx.blocking_send(Err(e));
+                }
+            }
+        });
+        Ok(futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        }))
+    }
+}
+
+fn compress_in_background(staged: PathBuf) {
+    tokio::task::spawn_blocking(move || {
+        let Some(target) = compressed_path(&staged) else {
+            return;
+        };
+        match gzip_file(&staged, &target) {
+            Ok(()) => info!("Rotated logger sink file to {:?}", target),
+            Err(e) => error!("Failed to compress rotated logger sink file {:?}: {}", staged, e),
+        }
+    });
+}
+
+/// `logger-<closed at>.jsonl` becomes `logger-<closed at>.jsonl.gz`.
+fn compressed_path(staged: &Path) -> Option<PathBuf> {
+    staged
+        .to_str()
+        .and_then(|s| s.strip_suffix(STAGED_SUFFIX))
+        .map(|base| PathBuf::from(format!("{}{}", base, ROTATED_SUFFIX)))
+}
+
+/// Writes to a temporary name first so exports only ever see complete `.gz` files.
+fn gzip_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    let partial = target.with_extension("gz.tmp");
+    let mut input = std::fs::File::open(source)?;
+    let output = std::fs::File::create(&partial)?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    std::fs::rename(&partial, target)?;
+    std::fs::remove_file(source)
+}
+
+/// Buffers export output and hands it to the response body in chunks.
+struct ChunkWriter {
+    tx: mpsc::Sender<std::io::Result<Bytes>>,
+    buffer: Vec<u8>,
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= EXPORT_CHUNK_BYTES {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::take(&mut self.buffer));
+        self.tx.blocking_send(Ok(chunk)).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::BrokenPipe, "export download was closed")
+        })
+    }
+}
+
+type ExportSource = Box<dyn BufRead + Send>;
+
+/// Opens the rotated files that may hold entries from `from_ms` on, oldest first, then
+/// the active file up to its current length.
+fn open_export_sources(dir: &Path, active_path: &Path, from_ms: Option<i64>) -> std::io::Result<Vec<ExportSource>> {
+    // A rotated file is either still staged or already compressed; prefer the latter
+    let mut rotated: BTreeMap<i64, (Option<PathBuf>, Option<PathBuf>)> = BTreeMap::new();
+    match std::fs::read_dir(dir) {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let Some(stem) = name.strip_prefix(ROTATED_PREFIX).filter(|_| !name.ends_with(COMPRESSING_SUFFIX)) else {
+                    continue;
+                };
+                let (time, compressed) = match stem.strip_suffix(ROTATED_SUFFIX) {
+                    Some(time) => (time, true),
+                    None => match stem.strip_suffix(STAGED_SUFFIX) {
+                        Some(time) => (time, false),
+                        None => continue,
+                    },
+                };
+                let Ok(closed_at) = chrono::NaiveDateTime::parse_from_str(time, ROTATED_TIME_FORMAT) else {
+                    continue;
+                };
+                let paths = rotated.entry(closed_at.and_utc().timestamp_millis()).or_default();
+                if compressed {
+                    paths.0 = Some(entry.path());
+                } else {
+                    paths.1 = Some(entry.path());
+                }
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    let mut sources: Vec<ExportSource> = Vec::new();
+    for (closed_at, (compressed, staged)) in rotated {
+        // Everything in a rotated file was written before it was closed
+        if from_ms.is_some_and(|from| closed_at < from) {
+            continue;
+        }
+        let compressed = match (compressed, staged) {
+            (Some(compressed), _) => compressed,
+            (None, Some(staged)) => match std::fs::File::open(&staged) {
+                Ok(file) => {
+                    sources.push(Box::new(BufReader::new(file)));
+                    continue;
+                }
+                // Finished compressing since the directory was listed
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => match compressed_path(&staged) {
+                    Some(compressed) => compressed,
+                    None => continue,
+                },
+                Err(e) => return Err(e),
+            },
+            (None, None) => continue,
+        };
+        let file = std::fs::File::open(compressed)?;
+        sources.push(Box::new(BufReader::new(GzDecoder::new(file))));
+    }
+
+    match std::fs::File::open(active_path) {
+        Ok(file) => {
+            // Lines appended after the export started are left out
+            let len = file.metadata()?.len();
+            sources.push(Box::new(BufReader::new(file.take(len))));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    Ok(sources)
+}
+
+fn copy_matching_lines(
+    reader: impl BufRead,
+    output: &mut impl Write,
+    from_ms: Option<i64>,
+    to_ms: Option<i64>,
+) -> std::io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        let Ok(entry) = serde_json::from_str::<serde_json::Value>(&line) else {
+            warn!("Skipping unreadable logger sink line");
+            continue;
+        };
+        let Some(timestamp) = entry["timestamp"]
+            .as_str()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.timestamp_millis())
+        else {
+            continue;
+        };
+        if from_ms.is_some_and(|from| timestamp < from) || to_ms.is_some_and(|to| timestamp > to) {
+            continue;
+        }
+        output.write_all(line.as_bytes())?;
+        output.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    fn sink_in(dir: &Path, max_bytes: u64) -> LogFileSink {
+        LogFileSink {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            rotate_after: Duration::from_secs(3600),
+            active: Mutex::new(None),
+        }
+    }
+
+    async fn exported(sink: &LogFileSink, from_ms: Option<i64>, to_ms: Option<i64>) -> Vec<i64> {
+        let mut body = Vec::new();
+        let mut chunks = Box::pin(sink.export(from_ms, to_ms).await.unwrap());
+        while let Some(chunk) = chunks.next().await {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+        String::from_utf8(body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["n"].as_i64().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn export_spans_rotated_and_active_files_once() {
+        let dir = std::env::temp_dir().join(format!("log-sink-{}", uuid::Uuid::new_v4()));
+        let sink = sink_in(&dir, 200);
+        let base = chrono::Utc::now();
+        for n in 0..10 {
+            let timestamp = (base + chrono::Duration::seconds(n)).to_rfc3339();
+            sink.write(&serde_json::json!({ "n": n, "timestamp": timestamp })).await.unwrap();
+        }
+
+        // Staged and compressed files must both be picked up, whatever the timing
+        assert_eq!(exported(&sink, None, None).await, (0..10).collect::<Vec<_>>());
+        for _ in 0..100 {
+            let staged = std::fs::read_dir(&dir)
+                .unwrap()
+                .flatten()
+                .any(|e| e.file_name().to_string_lossy().starts_with(ROTATED_PREFIX)
+                    && !e.file_name().to_string_lossy().ends_with(ROTATED_SUFFIX));
+            if !staged {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(exported(&sink, None, None).await, (0..10).collect::<Vec<_>>());
+
+        let to_ms = (base + chrono::Duration::seconds(4)).timestamp_millis();
+        assert_eq!(exported(&sink, None, Some(to_ms)).await, (0..5).collect::<Vec<_>>());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,15 +1,23 @@
 use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-use tokio::sync::{broadcast, Mutex};
+use tokio::sync::{broadcast, watch, Mutex};
 use warp::{Filter, ws::WebSocket};
 use serde::{Deserialize, Serialize};
 use tracing::{info, error, warn, debug};
 
+mod click_guard;
+mod dedup;
 mod handlers;
+mod heartbeats;
+mod log_sink;
+mod management;
+mod metrics;
 mod rabbitmq;
 mod redis_store;
 
 use handlers::*;
+use dedup::Deduplicator;
 use rabbitmq::RabbitMQConnection;
 use redis_store::RedisStore;
 
@@ -23,8 +31,20 @@ pub struct WebSocketMessage {
 pub struct AppState {
     pub rabbit: Arc<RabbitMQConnection>,
     pub broadcast_tx: broadcast::Sender<WebSocketMessage>,
-    pub game_scores: Arc<Mutex<HashMap<String, u32>>>,
+    pub game_rooms: Arc<Mutex<HashMap<String, game::GameRoom>>>,
+    pub click_guard: Arc<Mutex<click_guard::ClickGuard>>,
+    pub game_subscribers: Arc<Mutex<HashMap<String, game::GameSubscriber>>>,
+    pub remote_workers: Arc<Mutex<heartbeats::WorkerRegistry<workers::RemoteWorker>>>,
+    pub rpc_workers: Arc<Mutex<rpc::RpcWorkerRegistry>>,
+    pub task_cancellations: Arc<Mutex<workers::TaskCancellations>>,
+    pub worker_dedup: Arc<Deduplicator>,
+    pub logger_dedup: Arc<Deduplicator>,
+    pub log_sink: Option<Arc<log_sink::LogFileSink>>,
+    pub redelivery_demo: Arc<AtomicBool>,
+    pub worker_metrics: Arc<Mutex<metrics::WorkerMetrics>>,
+    pub worker_pool: Arc<Mutex<workers::WorkerPool>>,
     pub redis: Arc<RedisStore>,
+    pub management: Arc<management::ManagementClient>,
 }
 
 #[tokio::main]
@@ -42,22 +62,52 @@ async fn main() {
 
     info!("Setting up broadcast channel and game state");
     let (broadcast_tx, _) = broadcast::channel(100);
-    let game_scores = Arc::new(Mutex::new(HashMap::new()));
+    let game_rooms = Arc::new(Mutex::new(HashMap::from([(
+        game::DEFAULT_ROOM.to_string(),
+        game::GameRoom::new(game::DEFAULT_ROOM),
+    )])));
 
     let state = AppState {
         rabbit,
         broadcast_tx,
-        game_scores,
+        game_rooms,
+        click_guard: Arc::new(Mutex::new(click_guard::ClickGuard::from_env())),
+        game_subscribers: Arc::new(Mutex::new(HashMap::new())),
+        remote_workers: Arc::new(Mutex::new(heartbeats::WorkerRegistry::default())),
+        rpc_workers: Arc::new(Mutex::new(rpc::RpcWorkerRegistry::default())),
+        task_cancellations: Arc::new(Mutex::new(workers::TaskCancellations::default())),
+        worker_dedup: Arc::new(Deduplicator::from_env(redis.client(), "workers")),
+        logger_dedup: Arc::new(Deduplicator::from_env(redis.client(), "logger")),
+        log_sink: log_sink::LogFileSink::from_env().map(Arc::new),
+        redelivery_demo: Arc::new(AtomicBool::new(false)),
+        worker_metrics: Arc::new(Mutex::new(metrics::WorkerMetrics::default())),
+        worker_pool: Arc::new(Mutex::new(workers::WorkerPool::default())),
         redis,
+        management: Arc::new(management::ManagementClient::from_env()),
     };
 
     let state = Arc::new(state);
     info!("Application state initialized");
 
+    info!("Starting message_logger consumer");
+    tokio::spawn(logger::start_logger_consumer(state.clone()));
+    tokio::spawn(logger::start_history_retention(state.clone()));
+    tokio::spawn(logger::start_sink_rotation(state.clone()));
+
+    info!("Starting standalone worker event listener");
+    tokio::spawn(workers::start_worker_event_listener(state.clone()));
+    tokio::spawn(workers::start_metrics_broadcaster(state.clone()));
+
+    info!("Starting RPC worker heartbeat listener");
+    tokio::spawn(rpc::start_heartbeat_listener(state.clone()));
+
+    info!("Starting queue stats broadcaster");
+    tokio::spawn(simulator::start_stats_broadcaster(state.clone()));
+
     info!("Configuring CORS policy");
     let cors = warp::cors()
         .allow_any_origin()
-        .allow_headers(vec!["content-type"])
+        .allow_headers(vec!["content-type", "x-request-id"])
         .allow_methods(vec!["GET", "POST", "OPTIONS"]);
 
     let api_state = state.clone();
@@ -69,6 +119,22 @@ async fn main() {
         .and(with_state(api_state.clone()))
         .and_then(logger::send_message);
 
+    let logger_history_route = warp::path("api")
+        .and(warp::path("logger"))
+        .and(warp::path("history"))
+        .and(warp::get())
+        .and(warp::query::<logger::HistoryQuery>())
+        .and(with_state(api_state.clone()))
+        .and_then(logger::get_history);
+
+    let logger_export_route = warp::path("api")
+        .and(warp::path("logger"))
+        .and(warp::path("export"))
+        .and(warp::get())
+        .and(warp::query::<logger::ExportQuery>())
+        .and(with_state(api_state.clone()))
+        .and_then(logger::export_log_file);
+
     let workers_route = warp::path("api")
         .and(warp::path("workers"))
         .and(warp::path("submit"))
@@ -77,11 +143,50 @@ async fn main() {
         .and(with_state(api_state.clone()))
         .and_then(workers::submit_number);
 
+    let workers_cancel_route = warp::path("api")
+        .and(warp::path("workers"))
+        .and(warp::path("cancel"))
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_state(api_state.clone()))
+        .and_then(workers::cancel_task);
+
+    let redelivery_demo_route = warp::path("api")
+        .and(warp::path("workers"))
+        .and(warp::path("redelivery-demo"))
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_state(api_state.clone()))
+        .and_then(workers::set_redelivery_demo);
+
+    let workers_metrics_route = warp::path("api")
+        .and(warp::path("workers"))
+        .and(warp::path("metrics"))
+        .and(warp::get())
+        .and(with_state(api_state.clone()))
+        .and_then(workers::get_metrics);
+
+    let workers_scale_route = warp::path("api")
+        .and(warp::path("workers"))
+        .and(warp::path("scale"))
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_state(api_state.clone()))
+        .and_then(workers::scale_workers);
+
+    let workers_list_route = warp::path("api")
+        .and(warp::path("workers"))
+        .and(warp::path("list"))
+        .and(warp::get())
+        .and(with_state(api_state.clone()))
+        .and_then(workers::list_workers);
+
     let game_click_route = warp::path("api")
         .and(warp::path("game"))
         .and(warp::path("click"))
         .and(warp::post())
         .and(warp::body::json())
+        .and(warp::addr::remote())
         .and(with_state(api_state.clone()))
         .and_then(game::handle_click);
 
@@ -89,9 +194,81 @@ async fn main() {
         .and(warp::path("game"))
         .and(warp::path("scores"))
         .and(warp::get())
+        .and(warp::query::<game::ScoresQuery>())
         .and(with_state(api_state.clone()))
         .and_then(game::get_scores);
 
+    let game_leaderboard_route = warp::path("api")
+        .and(warp::path("game"))
+        .and(warp::path("leaderboard"))
+        .and(warp::get())
+        .and(warp::query::<game::LeaderboardQuery>())
+        .and(with_state(api_state.clone()))
+        .and_then(game::get_leaderboard);
+
+    let game_rounds_route = warp::path("api")
+        .and(warp::path("game"))
+        .and(warp::path("rounds"))
+        .and(warp::get())
+        .and(warp::query::<game::RoundsQuery>())
+        .and(with_state(api_state.clone()))
+        .and_then(game::get_rounds);
+
+    let game_rooms_route = warp::path("api")
+        .and(warp::path("game"))
+        .and(warp::path("rooms"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_state(api_state.clone()))
+        .and_then(game::list_rooms);
+
+    let game_room_route = warp::path("api")
+        .and(warp::path("game"))
+        .and(warp::path("rooms"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_state(api_state.clone()))
+        .and_then(game::get_room);
+
+    let game_join_route = warp::path("api")
+        .and(warp::path("game"))
+        .and(warp::path("rooms"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("join"))
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_state(api_state.clone()))
+        .and_then(game::join_room);
+
+    let game_leave_route = warp::path("api")
+        .and(warp::path("game"))
+        .and(warp::path("rooms"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("leave"))
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_state(api_state.clone()))
+        .and_then(game::leave_room);
+
+    let game_start_route = warp::path("api")
+        .and(warp::path("game"))
+        .and(warp::path("rooms"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("start"))
+        .and(warp::post())
+        .and(with_state(api_state.clone()))
+        .and_then(game::start_round);
+
+    let game_reset_route = warp::path("api")
+        .and(warp::path("game"))
+        .and(warp::path("rooms"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("reset"))
+        .and(warp::post())
+        .and(with_state(api_state.clone()))
+        .and_then(game::reset_room);
+
     let rpc_route = warp::path("api")
         .and(warp::path("rpc"))
         .and(warp::path("status"))
@@ -99,6 +276,75 @@ async fn main() {
         .and(with_state(api_state.clone()))
         .and_then(rpc::check_status);
 
+    let rpc_workers_route = warp::path("api")
+        .and(warp::path("rpc"))
+        .and(warp::path("workers"))
+        .and(warp::get())
+        .and(with_state(api_state.clone()))
+        .and_then(rpc::list_workers);
+
+    let rpc_scatter_route = warp::path("api")
+        .and(warp::path("rpc"))
+        .and(warp::path("scatter"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_state(api_state.clone()))
+        .and_then(rpc::scatter);
+
+    let rpc_method_route = warp::path("api")
+        .and(warp::path("rpc"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::header::optional::<String>("x-request-id"))
+        .and(warp::body::json())
+        .and(with_state(api_state.clone()))
+        .and_then(rpc::call_method);
+
+    let jsonrpc_route = warp::path("api")
+        .and(warp::path("jsonrpc"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::bytes())
+        .and(with_state(api_state.clone()))
+        .and_then(rpc::jsonrpc);
+
+    let broker_exchanges_route = warp::path("api")
+        .and(warp::path("broker"))
+        .and(warp::path("exchanges"))
+        .and(warp::get())
+        .and(with_state(api_state.clone()))
+        .and_then(broker::list_exchanges);
+
+    let broker_bindings_route = warp::path("api")
+        .and(warp::path("broker"))
+        .and(warp::path("bindings"))
+        .and(warp::get())
+        .and(with_state(api_state.clone()))
+        .and_then(broker::list_bindings);
+
+    let broker_channels_route = warp::path("api")
+        .and(warp::path("broker"))
+        .and(warp::path("channels"))
+        .and(warp::get())
+        .and(with_state(api_state.clone()))
+        .and_then(broker::list_channels);
+
+    let broker_consumers_route = warp::path("api")
+        .and(warp::path("broker"))
+        .and(warp::path("consumers"))
+        .and(warp::get())
+        .and(with_state(api_state.clone()))
+        .and_then(broker::list_consumers);
+
+    let broker_rates_route = warp::path("api")
+        .and(warp::path("broker"))
+        .and(warp::path("rates"))
+        .and(warp::get())
+        .and(with_state(api_state.clone()))
+        .and_then(broker::get_rates);
+
     let simulator_route = warp::path("api")
         .and(warp::path("simulator"))
         .and(warp::path("simulate"))
@@ -179,10 +425,34 @@ async fn main() {
 
     info!("Setting up API routes");
     let routes = logger_route
+        .or(logger_history_route)
+        .or(logger_export_route)
         .or(workers_route)
+        .or(workers_cancel_route)
+        .or(redelivery_demo_route)
+        .or(workers_list_route)
+        .or(workers_metrics_route)
+        .or(workers_scale_route)
         .or(game_click_route)
         .or(game_scores_route)
+        .or(game_leaderboard_route)
+        .or(game_rounds_route)
+        .or(game_rooms_route)
+        .or(game_room_route)
+        .or(game_join_route)
+        .or(game_leave_route)
+        .or(game_start_route)
+        .or(game_reset_route)
         .or(rpc_route)
+        .or(rpc_workers_route)
+        .or(rpc_scatter_route)
+        .or(rpc_method_route)
+        .or(jsonrpc_route)
+        .or(broker_exchanges_route)
+        .or(broker_bindings_route)
+        .or(broker_channels_route)
+        .or(broker_consumers_route)
+        .or(broker_rates_route)
         .or(simulator_route)
         .or(queue_stats_route)
         .or(drawing_event_route)
@@ -197,7 +467,42 @@ async fn main() {
 
     info!("All routes configured successfully");
     info!("Server starting on http://localhost:3030");
-    warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
+    let (_, server) = warp::serve(routes)
+        .bind_with_graceful_shutdown(([127, 0, 0, 1], 3030), shutdown_signal());
+    server.await;
+
+    info!("Server stopped accepting requests, draining workers");
+    workers::shutdown_workers(state).await;
+    info!("Shutdown complete");
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl+C, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+    }
 }
 
 fn with_state(state: Arc<AppState>) -> impl Filter<Extract = (Arc<AppState>,), Error = std::convert::Infallible> + Clone {
@@ -211,10 +516,52 @@ async fn handle_websocket(ws: WebSocket, state: Arc<AppState>) {
     info!("New WebSocket connection established");
     let (mut ws_tx, mut ws_rx) = ws.split();
     let mut broadcast_rx = state.broadcast_tx.subscribe();
+    let (logger_filter_tx, logger_filter_rx) = watch::channel(logger::LoggerFilter::default());
+    let (game_subscription_tx, mut game_subscription_rx) = watch::channel(None::<game::GameSubscription>);
 
     let broadcast_task = tokio::spawn(async move {
         debug!("Starting WebSocket broadcast task");
-        while let Ok(msg) = broadcast_rx.recv().await {
+        // Game events come from this client's own queue on the game_scores exchange
+        let mut game_feed: Option<game::GameFeed> = None;
+        loop {
+            let msg = tokio::select! {
+                msg = broadcast_rx.recv() => match msg {
+                    Ok(msg) => msg,
+                    Err(_) => break,
+                },
+                event = next_game_event(&mut game_feed) => match event {
+                    Some(event) => WebSocketMessage {
+                        demo_type: "game".to_string(),
+                        data: event,
+                    },
+                    None => {
+                        warn!("Game subscriber queue closed");
+                        if let Some(feed) = game_feed.take() {
+                            feed.close(&state).await;
+                        }
+                        continue;
+                    }
+                },
+                changed = game_subscription_rx.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    if let Some(feed) = game_feed.take() {
+                        feed.close(&state).await;
+                    }
+                    let subscription = game_subscription_rx.borrow_and_update().clone();
+                    if let Some(subscription) = subscription {
+                        match game::GameFeed::subscribe(&state, subscription).await {
+                            Ok(feed) => game_feed = Some(feed),
+                            Err(e) => error!("Failed to subscribe WebSocket client to game events: {}", e),
+                        }
+                    }
+                    continue;
+                }
+            };
+            if msg.demo_type == "logger" && !logger_filter_rx.borrow().matches(&msg.data) {
+                continue;
+            }
             debug!("Broadcasting message to WebSocket client: {}", msg.demo_type);
             if let Ok(json) = serde_json::to_string(&msg) {
                 if ws_tx.send(Message::text(json)).await.is_err() {
@@ -225,17 +572,26 @@ async fn handle_websocket(ws: WebSocket, state: Arc<AppState>) {
                 error!("Failed to serialize WebSocket message");
             }
         }
+        if let Some(feed) = game_feed.take() {
+            feed.close(&state).await;
+        }
         debug!("WebSocket broadcast task ended");
     });
 
     let receive_task = tokio::spawn(async move {
         debug!("Starting WebSocket receive task");
         while let Some(result) = ws_rx.next().await {
-            if let Err(e) = result {
-                warn!("WebSocket receive error: {}", e);
-                break;
-            }
+            let msg = match result {
+                Ok(msg) => msg,
+                Err(e) => {
+                    warn!("WebSocket receive error: {}", e);
+                    break;
+                }
+            };
             debug!("Received WebSocket message from client");
+            if let Ok(text) = msg.to_str() {
+                handle_client_message(text, &logger_filter_tx, &game_subscription_tx);
+            }
         }
         debug!("WebSocket receive task ended");
     });
@@ -249,4 +605,49 @@ async fn handle_websocket(ws: WebSocket, state: Arc<AppState>) {
         },
     }
     info!("WebSocket connection closed");
-}
\ No newline at end of file
+}
+
+async fn next_game_event(game_feed: &mut Option<game::GameFeed>) -> Option<serde_json::Value> {
+    match game_feed {
+        Some(feed) => feed.next_event().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Handles control messages sent by a WebSocket client, e.g.
+/// `{"action": "logger_filter", "min_severity": "error", "source": "billing"}` or
+/// `{"action": "subscribe_game", "room": "default"}`.
+fn handle_client_message(
+    text: &str,
+    logger_filter_tx: &watch::Sender<logger::LoggerFilter>,
+    game_subscription_tx: &watch::Sender<Option<game::GameSubscription>>,
+) {
+    let request: serde_json::Value = match serde_json::from_str(text) {
+        Ok(request) => request,
+        Err(e) => {
+            debug!("Ignoring non-JSON WebSocket message: {}", e);
+            return;
+        }
+    };
+
+    match request["action"].as_str() {
+        Some("logger_filter") => match logger::LoggerFilter::from_request(&request) {
+            Ok(filter) => {
+                info!("WebSocket client set logger filter: {:?}", filter);
+                let _ = logger_filter_tx.send(filter);
+            }
+            Err(e) => warn!("Rejected logger filter from WebSocket client: {}", e),
+        },
+        Some("subscribe_game") => {
+            let subscription = game::GameSubscription::from_request(&request);
+            info!("WebSocket client subscribed to game events: {:?}", subscription);
+            let _ = game_subscription_tx.send(Some(subscription));
+        }
+        Some("unsubscribe_game") => {
+            info!("WebSocket client unsubscribed from game events");
+            let _ = game_subscription_tx.send(None);
+        }
+        Some(action) => warn!("Unknown WebSocket client action: {}", action),
+        None => debug!("Ignoring WebSocket message without action"),
+    }
+}
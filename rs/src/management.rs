@@ -0,0 +1,468 @@
+use std::time::Duration;
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{info, warn, debug, instrument};
+
+const DEFAULT_URL: &str = "http://localhost:15672";
+const DEFAULT_USER: &str = "guest";
+const DEFAULT_VHOST: &str = "/";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug)]
+pub enum ManagementError {
+    /// The API could not be reached or the response body could not be read.
+    Request(reqwest::Error),
+    /// The API answered with a non-success status, e.g. 401 for bad credentials.
+    Status { status: u16, body: String },
+}
+
+impl std::fmt::Display for ManagementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManagementError::Request(e) => write!(f, "Management API request failed: {}", e),
+            ManagementError::Status { status, body } => {
+                write!(f, "Management API answered {}: {}", status, body)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManagementError {}
+
+impl From<reqwest::Error> for ManagementError {
+    fn from(e: reqwest::Error) -> Self {
+        ManagementError::Request(e)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExchangeInfo {
+    pub name: String,
+    pub exchange_type: String,
+    pub durable: bool,
+    pub auto_delete: bool,
+    pub internal: bool,
+    pub publish_in_rate: f64,
+    pub publish_out_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BindingInfo {
+    pub source: String,
+    pub destination: String,
+    pub destination_type: String,
+    pub routing_key: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelInfo {
+    pub name: String,
+    pub connection: String,
+    pub number: u64,
+    pub consumer_count: u64,
+    pub prefetch_count: u64,
+    pub messages_unacknowledged: u64,
+    pub publish_rate: f64,
+    pub deliver_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsumerInfo {
+    pub consumer_tag: String,
+    pub queue: String,
+    pub channel: String,
+    pub ack_required: bool,
+    pub exclusive: bool,
+    pub prefetch_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueRates {
+    pub name: String,
+    pub messages: u64,
+    pub messages_ready: u64,
+    pub messages_unacknowledged: u64,
+    pub consumers: u64,
+    pub publish_rate: f64,
+    pub deliver_rate: f64,
+    pub ack_rate: f64,
+}
+
+/// Broker-wide message rates (per second) and object counts from `/api/overview`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BrokerRates {
+    pub publish_rate: f64,
+    pub deliver_rate: f64,
+    pub ack_rate: f64,
+    pub redeliver_rate: f64,
+    pub messages: u64,
+    pub messages_ready: u64,
+    pub messages_unacknowledged: u64,
+    pub connections: u64,
+    pub channels: u64,
+    pub exchanges: u64,
+    pub queues: u64,
+    pub consumers: u64,
+}
+
+/// Client for the RabbitMQ management HTTP API. Passive declares only answer for one
+/// named queue or exchange at a time; this lists everything in the vhost along with the
+/// bindings, channels, consumers and message rates the broker keeps track of.
+#[derive(Debug, Clone)]
+pub struct ManagementClient {
+    http: reqwest::Client,
+    base_url: String,
+    user: String,
+    password: String,
+    vhost: String,
+}
+
+impl ManagementClient {
+    pub fn new(base_url: &str, user: &str, password: &str, vhost: &str) -> Self {
+        info!("Using RabbitMQ management API at {} (vhost {})", base_url, vhost);
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_else(|e| {
+                warn!("Failed to configure management API client, using defaults: {}", e);
+                reqwest::Client::new()
+            });
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            user: user.to_string(),
+            password: password.to_string(),
+            vhost: vhost.to_string(),
+        }
+    }
+
+    /// Uses `RABBITMQ_MANAGEMENT_URL` (default `http://localhost:15672`),
+    /// `RABBITMQ_MANAGEMENT_USER` and `RABBITMQ_MANAGEMENT_PASSWORD` (default `guest`) and
+    /// `RABBITMQ_MANAGEMENT_VHOST` (default `/`).
+    pub fn from_env() -> Self {
+        let env = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+        Self::new(
+            &env("RABBITMQ_MANAGEMENT_URL", DEFAULT_URL),
+            &env("RABBITMQ_MANAGEMENT_USER", DEFAULT_USER),
+            &env("RABBITMQ_MANAGEMENT_PASSWORD", DEFAULT_USER),
+            &env("RABBITMQ_MANAGEMENT_VHOST", DEFAULT_VHOST),
+        )
+    }
+
+    #[instrument(skip(self))]
+    pub async fn exchanges(&self) -> Result<Vec<ExchangeInfo>, ManagementError> {
+        let exchanges = self.get_list(&format!("exchanges/{}", self.vhost_path())).await?;
+        Ok(exchanges
+            .iter()
+            .map(|e| ExchangeInfo {
+                // The default exchange is listed with an empty name
+                name: match e["name"].as_str().unwrap_or_default() {
+                    "" => "(AMQP default)".to_string(),
+                    name => name.to_string(),
+                },
+                exchange_type: string(&e["type"]),
+                durable: e["durable"].as_bool().unwrap_or(false),
+                auto_delete: e["auto_delete"].as_bool().unwrap_or(false),
+                internal: e["internal"].as_bool().unwrap_or(false),
+                publish_in_rate: rate(&e["message_stats"], "publish_in_details"),
+                publish_out_rate: rate(&e["message_stats"], "publish_out_details"),
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn bindings(&self) -> Result<Vec<BindingInfo>, ManagementError> {
+        let bindings = self.get_list(&format!("bindings/{}", self.vhost_path())).await?;
+        Ok(bindings
+            .iter()
+            .map(|b| BindingInfo {
+                source: string(&b["source"]),
+                destination: string(&b["destination"]),
+                destination_type: string(&b["destination_type"]),
+                routing_key: string(&b["routing_key"]),
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn channels(&self) -> Result<Vec<ChannelInfo>, ManagementError> {
+        let channels = self.get_list(&format!("vhosts/{}/channels", self.vhost_path())).await?;
+        Ok(channels
+            .iter()
+            .map(|c| ChannelInfo {
+                name: string(&c["name"]),
+                connection: string(&c["connection_details"]["name"]),
+                number: count(&c["number"]),
+                consumer_count: count(&c["consumer_count"]),
+                prefetch_count: count(&c["prefetch_count"]),
+                messages_unacknowledged: count(&c["messages_unacknowledged"]),
+                publish_rate: rate(&c["message_stats"], "publish_details"),
+                deliver_rate: rate(&c["message_stats"], "deliver_get_details"),
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn consumers(&self) -> Result<Vec<ConsumerInfo>, ManagementError> {
+        let consumers = self.get_list(&format!("consumers/{}", self.vhost_path())).await?;
+        Ok(consumers
+            .iter()
+            .map(|c| ConsumerInfo {
+                consumer_tag: string(&c["consumer_tag"]),
+                queue: string(&c["queue"]["name"]),
+                channel: string(&c["channel_details"]["name"]),
+                ack_required: c["ack_required"].as_bool().unwrap_or(false),
+                exclusive: c["exclusive"].as_bool().unwrap_or(false),
+                prefetch_count: count(&c["prefetch_count"]),
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn queues(&self) -> Result<Vec<QueueRates>, ManagementError> {
+        let queues = self.get_list(&format!("queues/{}", self.vhost_path())).await?;
+        Ok(queues
+            .iter()
+            .map(|q| QueueRates {
+                name: string(&q["name"]),
+                messages: count(&q["messages"]),
+                messages_ready: count(&q["messages_ready"]),
+                messages_unacknowledged: count(&q["messages_unacknowledged"]),
+                consumers: count(&q["consumers"]),
+                publish_rate: rate(&q["message_stats"], "publish_details"),
+                deliver_rate: rate(&q["message_stats"], "deliver_get_details"),
+                ack_rate: rate(&q["message_stats"], "ack_details"),
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn rates(&self) -> Result<BrokerRates, ManagementError> {
+        let overview = self.get("overview").await?;
+        let stats = &overview["message_stats"];
+        let totals = &overview["queue_totals"];
+        let objects = &overview["object_totals"];
+        Ok(BrokerRates {
+            publish_rate: rate(stats, "publish_details"),
+            deliver_rate: rate(stats, "deliver_get_details"),
+            ack_rate: rate(stats, "ack_details"),
+            redeliver_rate: rate(stats, "redeliver_details"),
+            messages: count(&totals["messages"]),
+            messages_ready: count(&totals["messages_ready"]),
+            messages_unacknowledged: count(&totals["messages_unacknowledged"]),
+            connections: count(&objects["connections"]),
+            channels: count(&objects["channels"]),
+            exchanges: count(&objects["exchanges"]),
+            queues: count(&objects["queues"]),
+            consumers: count(&objects["consumers"]),
+        })
+    }
+
+    /// The vhost as a path segment; the default vhost `/` has to be sent as `%2F`.
+    fn vhost_path(&self) -> String {
+        percent_encode(&self.vhost)
+    }
+
+    async fn get(&self, path: &str) -> Result<Value, ManagementError> {
+        let url = format!("{}/api/{}", self.base_url, path);
+        debug!("GET {}", url);
+        let response = self
+            .http
+            .get(&url)
+            .basic_auth(&self.user, Some(&self.password))
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            warn!("Management API answered {} for {}", status, url);
+            return Err(ManagementError::Status { status: status.as_u16(), body });
+        }
+        Ok(response.json::<Value>().await?)
+    }
+
+    async fn get_list(&self, path: &str) -> Result<Vec<Value>, ManagementError> {
+        match self.get(path).await? {
+            Value::Array(items) => Ok(items),
+            other => {
+                warn!("Expected a list from management API {}, got {}", path, other);
+                Ok(Vec::new())
+            }
+        }
+    }
+}
+
+/// Percent-encodes everything but RFC 3986 unreserved characters, so a vhost name can
+/// be used as a single path segment whatever it contains.
+fn percent_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn string(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+fn count(value: &Value) -> u64 {
+    value.as_u64().unwrap_or(0)
+}
+
+/// `message_stats.<details>.rate`; the broker leaves stats out until something happened.
+fn rate(message_stats: &Value, details: &str) -> f64 {
+    message_stats[details]["rate"].as_f64().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use warp::Filter;
+
+    // "guest:guest"
+    const GUEST_AUTH: &str = "Basic Z3Vlc3Q6Z3Vlc3Q=";
+
+    /// Serves canned management API answers on a local port and returns its base URL.
+    /// Any path other than the ones below gets a 404, so a wrongly encoded vhost fails.
+    fn stub_api() -> String {
+        let authorized = warp::header::optional::<String>("authorization").and_then(
+            |auth: Option<String>| async move {
+                match auth.as_deref() {
+                    Some(GUEST_AUTH) => Ok(()),
+                    _ => Err(warp::reject::custom(Unauthorized)),
+                }
+            },
+        );
+
+        let exchanges = warp::path!("api" / "exchanges" / "%2F").map(|| {
+            warp::reply::json(&json!([
+                {
+                    "name": "",
+                    "type": "direct",
+                    "durable": true,
+                    "auto_delete": false,
+                    "internal": false,
+                    "message_stats": {
+                        "publish_in_details": { "rate": 2.5 },
+                        "publish_out_details": { "rate": 1.5 }
+                    }
+                },
+                {
+                    "name": "game_events",
+                    "type": "fanout",
+                    "durable": false,
+                    "auto_delete": true,
+                    "internal": false
+                }
+            ]))
+        });
+        let spaced_vhost_queues = warp::path!("api" / "queues" / "my%20vhost").map(|| {
+            warp::reply::json(&json!([{ "name": "tasks", "messages": 3, "consumers": 1 }]))
+        });
+        let overview = warp::path!("api" / "overview").map(|| {
+            warp::reply::json(&json!({
+                "message_stats": {
+                    "publish_details": { "rate": 10.0 },
+                    "ack_details": { "rate": 8.0 }
+                },
+                "queue_totals": { "messages": 7, "messages_ready": 5, "messages_unacknowledged": 2 },
+                "object_totals": { "connections": 1, "channels": 2, "exchanges": 9, "queues": 4, "consumers": 3 }
+            }))
+        });
+
+        let routes = warp::get()
+            .and(authorized)
+            .untuple_one()
+            .and(exchanges.or(spaced_vhost_queues).or(overview))
+            .recover(|rejection: warp::Rejection| async move {
+                if rejection.find::<Unauthorized>().is_some() {
+                    Ok(warp::reply::with_status("Not authorised", warp::http::StatusCode::UNAUTHORIZED))
+                } else {
+                    Err(rejection)
+                }
+            });
+
+        let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        format!("http://{}", addr)
+    }
+
+    #[derive(Debug)]
+    struct Unauthorized;
+
+    impl warp::reject::Reject for Unauthorized {}
+
+    #[test]
+    fn vhost_is_encoded_as_one_path_segment() {
+        let client = |vhost: &str| ManagementClient::new("http://localhost:15672", "guest", "guest", vhost);
+        assert_eq!(client("/").vhost_path(), "%2F");
+        assert_eq!(client("my vhost").vhost_path(), "my%20vhost");
+        assert_eq!(client("a/b?c#d%").vhost_path(), "a%2Fb%3Fc%23d%25");
+        assert_eq!(client("staging-1.eu_west~2").vhost_path(), "staging-1.eu_west~2");
+    }
+
+    #[tokio::test]
+    async fn exchanges_map_fields_and_rename_the_default_exchange() {
+        let client = ManagementClient::new(&stub_api(), "guest", "guest", "/");
+        let exchanges = client.exchanges().await.unwrap();
+
+        assert_eq!(exchanges.len(), 2);
+        let default = &exchanges[0];
+        assert_eq!(default.name, "(AMQP default)");
+        assert_eq!(default.exchange_type, "direct");
+        assert!(default.durable);
+        assert!(!default.auto_delete);
+        assert_eq!(default.publish_in_rate, 2.5);
+        assert_eq!(default.publish_out_rate, 1.5);
+
+        let game = &exchanges[1];
+        assert_eq!(game.name, "game_events");
+        assert_eq!(game.exchange_type, "fanout");
+        assert!(game.auto_delete);
+        assert_eq!(game.publish_in_rate, 0.0);
+        assert_eq!(game.publish_out_rate, 0.0);
+    }
+
+    #[tokio::test]
+    async fn rates_read_the_overview_and_default_missing_stats_to_zero() {
+        let client = ManagementClient::new(&stub_api(), "guest", "guest", "/");
+        let rates = client.rates().await.unwrap();
+
+        assert_eq!(rates.publish_rate, 10.0);
+        assert_eq!(rates.ack_rate, 8.0);
+        assert_eq!(rates.deliver_rate, 0.0);
+        assert_eq!(rates.redeliver_rate, 0.0);
+        assert_eq!(rates.messages, 7);
+        assert_eq!(rates.messages_ready, 5);
+        assert_eq!(rates.messages_unacknowledged, 2);
+        assert_eq!(rates.exchanges, 9);
+        assert_eq!(rates.consumers, 3);
+    }
+
+    #[tokio::test]
+    async fn vhost_with_a_space_reaches_the_encoded_path() {
+        let client = ManagementClient::new(&stub_api(), "guest", "guest", "my vhost");
+        let queues = client.queues().await.unwrap();
+
+        assert_eq!(queues.len(), 1);
+        assert_eq!(queues[0].name, "tasks");
+        assert_eq!(queues[0].messages, 3);
+        assert_eq!(queues[0].publish_rate, 0.0);
+    }
+
+    #[tokio::test]
+    async fn bad_credentials_are_reported_as_a_status_error() {
+        let client = ManagementClient::new(&stub_api(), "guest", "wrong", "/");
+        match client.exchanges().await {
+            Err(ManagementError::Status { status, body }) => {
+                assert_eq!(status, 401);
+                assert_eq!(body, "Not authorised");
+            }
+            other => panic!("expected a 401 status error, got {:?}", other),
+        }
+    }
+}
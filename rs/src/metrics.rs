@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use serde::Serialize;
+
+/// Upper bounds (inclusive, in milliseconds) of the latency histogram buckets.
+/// Anything slower lands in a final overflow bucket.
+const LATENCY_BUCKETS_MS: [u64; 10] = [50, 100, 250, 500, 1000, 2000, 3000, 5000, 10000, 30000];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistogramBucket {
+    pub le_ms: Option<u64>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyHistogram {
+    pub buckets: Vec<HistogramBucket>,
+    pub count: u64,
+    pub sum_ms: u64,
+    pub min_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        let mut buckets: Vec<HistogramBucket> = LATENCY_BUCKETS_MS
+            .iter()
+            .map(|le| HistogramBucket { le_ms: Some(*le), count: 0 })
+            .collect();
+        buckets.push(HistogramBucket { le_ms: None, count: 0 });
+        Self {
+            buckets,
+            count: 0,
+            sum_ms: 0,
+            min_ms: None,
+            max_ms: None,
+            p50_ms: None,
+            p95_ms: None,
+            p99_ms: None,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn observe(&mut self, value_ms: u64) {
+        if let Some(bucket) = self
+            .buckets
+            .iter_mut()
+            .find(|b| b.le_ms.is_none_or(|le| value_ms <= le))
+        {
+            bucket.count += 1;
+        }
+        self.count += 1;
+        self.sum_ms += value_ms;
+        self.min_ms = Some(self.min_ms.map_or(value_ms, |min| min.min(value_ms)));
+        self.max_ms = Some(self.max_ms.map_or(value_ms, |max| max.max(value_ms)));
+        self.p50_ms = self.percentile(0.50);
+        self.p95_ms = self.percentile(0.95);
+        self.p99_ms = self.percentile(0.99);
+    }
+
+    /// Estimates the `quantile` (0.0..=1.0) as the upper bound of the bucket holding
+    /// that rank, clamped to the observed min and max. `None` until something is observed.
+    pub fn percentile(&self, quantile: f64) -> Option<u64> {
+        let (min, max) = (self.min_ms?, self.max_ms?);
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        self.buckets.iter().find_map(|bucket| {
+            seen += bucket.count;
+            (seen >= rank).then(|| bucket.le_ms.unwrap_or(max).clamp(min, max))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaskStats {
+    pub processed: u64,
+    pub failed: u64,
+    pub retried: u64,
+    pub queue_wait: LatencyHistogram,
+    pub processing_time: LatencyHistogram,
+    pub last_activity: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Throughput and latency of the number doubler workers, broken down both by worker
+/// and by the queue the tasks came from.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WorkerMetrics {
+    pub workers: HashMap<String, TaskStats>,
+    pub queues: HashMap<String, TaskStats>,
+}
+
+impl WorkerMetrics {
+    fn stats_for(&mut self, worker_id: &str, queue: &str) -> [&mut TaskStats; 2] {
+        let now = chrono::Utc::now();
+        let worker = self.workers.entry(worker_id.to_string()).or_default();
+        worker.last_activity = Some(now);
+        let queue = self.queues.entry(queue.to_string()).or_default();
+        queue.last_activity = Some(now);
+        [worker, queue]
+    }
+
+    pub fn record_processed(
+        &mut self,
+        worker_id: &str,
+        queue: &str,
+        queue_wait_ms: Option<u64>,
+        processing_ms: u64,
+    ) {
+        for stats in self.stats_for(worker_id, queue) {
+            stats.processed += 1;
+            if let Some(wait) = queue_wait_ms {
+                stats.queue_wait.observe(wait);
+            }
+            stats.processing_time.observe(processing_ms);
+        }
+    }
+
+    pub fn record_failed(&mut self, worker_id: &str, queue: &str) {
+        for stats in self.stats_for(worker_id, queue) {
+            stats.failed += 1;
+        }
+    }
+
+    pub fn record_retried(&mut self, worker_id: &str, queue: &str) {
+        for stats in self.stats_for(worker_id, queue) {
+            stats.retried += 1;
+        }
+    }
+}
+
+/// Milliseconds between a `published_at` timestamp (epoch millis) and now.
+pub fn queue_wait_ms(published_at_ms: Option<i64>) -> Option<u64> {
+    published_at_ms.map(|published| (chrono::Utc::now().timestamp_millis() - published).max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket_counts(histogram: &LatencyHistogram) -> Vec<u64> {
+        histogram.buckets.iter().map(|b| b.count).collect()
+    }
+
+    #[test]
+    fn bucket_bounds_are_inclusive() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.observe(0);
+        histogram.observe(50);
+        histogram.observe(51);
+        histogram.observe(30000);
+        histogram.observe(30001);
+
+        assert_eq!(bucket_counts(&histogram), vec![2, 1, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+        assert_eq!(histogram.buckets.last().unwrap().le_ms, None);
+        assert_eq!(histogram.count, 5);
+        assert_eq!(histogram.sum_ms, 60102);
+        assert_eq!(histogram.min_ms, Some(0));
+        assert_eq!(histogram.max_ms, Some(30001));
+    }
+
+    #[test]
+    fn empty_histogram_has_no_percentiles() {
+        let histogram = LatencyHistogram::default();
+        assert_eq!(histogram.percentile(0.5), None);
+        assert_eq!(histogram.percentile(0.99), None);
+        assert_eq!(histogram.p50_ms, None);
+        assert_eq!(histogram.min_ms, None);
+    }
+
+    #[test]
+    fn single_sample_is_every_percentile() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.observe(70);
+        for quantile in [0.0, 0.5, 0.95, 1.0] {
+            assert_eq!(histogram.percentile(quantile), Some(70));
+        }
+        assert_eq!(histogram.p99_ms, Some(70));
+    }
+
+    #[test]
+    fn percentiles_use_bucket_upper_bounds() {
+        let mut histogram = LatencyHistogram::default();
+        for value in [10, 20, 30, 400, 40000] {
+            histogram.observe(value);
+        }
+        assert_eq!(histogram.percentile(0.5), Some(50));
+        assert_eq!(histogram.percentile(0.8), Some(500));
+        // The overflow bucket has no upper bound, so the max stands in for it
+        assert_eq!(histogram.percentile(1.0), Some(40000));
+    }
+
+    #[test]
+    fn records_per_worker_and_per_queue() {
+        let mut metrics = WorkerMetrics::default();
+        metrics.record_processed("1", "number_doubler", Some(20), 1500);
+        metrics.record_processed("2", "number_doubler", None, 2500);
+        metrics.record_failed("1", "number_doubler");
+        metrics.record_retried("2", "number_doubler");
+
+        let worker = &metrics.workers["1"];
+        assert_eq!((worker.processed, worker.failed, worker.retried), (1, 1, 0));
+        assert_eq!(worker.queue_wait.count, 1);
+        assert_eq!(worker.processing_time.max_ms, Some(1500));
+
+        let queue = &metrics.queues["number_doubler"];
+        assert_eq!((queue.processed, queue.failed, queue.retried), (2, 1, 1));
+        assert_eq!(queue.queue_wait.count, 1);
+        assert_eq!(queue.processing_time.count, 2);
+        assert!(queue.last_activity.is_some());
+    }
+
+    #[test]
+    fn queue_wait_is_never_negative() {
+        assert_eq!(queue_wait_ms(None), None);
+        let future = chrono::Utc::now().timestamp_millis() + 60_000;
+        assert_eq!(queue_wait_ms(Some(future)), Some(0));
+    }
+}
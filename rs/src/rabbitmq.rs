@@ -1,16 +1,171 @@
-use futures_util::TryStreamExt;
 use lapin::{
-    options::*, types::FieldTable, BasicProperties, Channel, Connection, ConnectionProperties,
-    Consumer, ExchangeKind,
+    message::Delivery,
+    options::*,
+    types::{AMQPValue, FieldTable},
+    BasicProperties, Channel, Connection, ConnectionProperties, Consumer, ExchangeKind,
 };
+use serde::Serialize;
 use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::mpsc;
 use uuid::Uuid;
 use tracing::{info, error, warn, debug, instrument};
 
+/// Queues declared by `setup_queues_and_exchanges`.
+pub const DECLARED_QUEUES: &[&str] = &[
+    "message_logger",
+    "number_doubler",
+    "rpc_requests",
+    "rpc_replies",
+    "rpc_dead_letters",
+];
+
+/// Exchanges declared by `setup_queues_and_exchanges`, with their type.
+pub const DECLARED_EXCHANGES: &[(&str, &str)] = &[
+    ("worker_events", "fanout"),
+    ("worker_control", "fanout"),
+    ("game_scores", "fanout"),
+    ("rpc_heartbeats", "fanout"),
+    ("rpc_broadcast", "fanout"),
+    ("drawing_fanout", "fanout"),
+    ("collaborative_drawing", "fanout"),
+];
+
+/// Live counts for one queue, read with a passive declare.
+#[derive(Debug, Clone)]
+pub struct QueueDepth {
+    pub message_count: u32,
+    pub consumer_count: u32,
+}
+
+/// Content type marking JSON-RPC 2.0 requests on `rpc_requests`.
+pub const JSONRPC_CONTENT_TYPE: &str = "application/json-rpc";
+
+/// A reply to `rpc_call`, with the round trip as seen by the caller.
+#[derive(Debug, Clone)]
+pub struct RpcReply {
+    pub payload: Value,
+    pub correlation_id: String,
+    pub latency_ms: u64,
+    /// Instance id of the worker that answered, from the reply's `app_id` property.
+    pub worker_id: Option<String>,
+    /// Progress parts the worker sent before this final reply.
+    pub parts: u32,
+}
+
+/// A progress part of a streaming reply: any reply whose `x-final` header is false.
+#[derive(Debug, Clone)]
+pub struct RpcPart {
+    pub correlation_id: String,
+    /// Position in the reply stream, from the `x-seq` header.
+    pub seq: u32,
+    pub payload: Value,
+    pub worker_id: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+/// Everything gathered by `scatter_call` before it stopped waiting.
+#[derive(Debug)]
+pub struct ScatterReplies {
+    pub correlation_id: String,
+    /// Replies in arrival order, error envelopes included.
+    pub replies: Vec<RpcReply>,
+    /// Whether the deadline passed before the expected number of replies arrived.
+    pub timed_out: bool,
+    pub elapsed_ms: u64,
+}
+
+/// Error reply envelope sent by the RPC worker:
+/// `{"success": false, "error": {"code": ..., "message": ..., "details": ...}}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteError {
+    pub code: String,
+    pub message: String,
+    pub details: Option<Value>,
+    pub correlation_id: String,
+    pub worker_id: Option<String>,
+    pub latency_ms: u64,
+}
+
+impl RemoteError {
+    /// Reads the error envelope out of a reply, if the reply is a failure.
+    pub fn from_reply(reply: &RpcReply) -> Option<Self> {
+        if reply.payload["success"].as_bool() != Some(false) {
+            return None;
+        }
+        let error = &reply.payload["error"];
+        // Workers from before the envelope sent the message as a plain string
+        let (code, message) = match error.as_str() {
+            Some(message) => ("remote_error", message),
+            None => (
+                error["code"].as_str().unwrap_or("remote_error"),
+                error["message"].as_str().unwrap_or("RPC worker reported an error"),
+            ),
+        };
+        Some(Self {
+            code: code.to_string(),
+            message: message.to_string(),
+            details: error.get("details").filter(|d| !d.is_null()).cloned(),
+            correlation_id: reply.correlation_id.clone(),
+            worker_id: reply.worker_id.clone(),
+            latency_ms: reply.latency_ms,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum RpcError {
+    /// The worker answered with an error envelope.
+    Remote(Box<RemoteError>),
+    /// No reply arrived in time.
+    Timeout { correlation_id: String, after: Duration },
+    /// The request could not be sent or the reply could not be read.
+    Transport(String),
+}
+
+impl RpcError {
+    pub fn code(&self) -> &str {
+        match self {
+            RpcError::Remote(e) => &e.code,
+            RpcError::Timeout { .. } => "timeout",
+            RpcError::Transport(_) => "transport_error",
+        }
+    }
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpcError::Remote(e) => write!(f, "{} ({})", e.message, e.code),
+            RpcError::Timeout { correlation_id, after } => {
+                write!(f, "No reply to {} within {:?}", correlation_id, after)
+            }
+            RpcError::Transport(e) => write!(f, "RPC transport error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+impl From<lapin::Error> for RpcError {
+    fn from(e: lapin::Error) -> Self {
+        RpcError::Transport(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for RpcError {
+    fn from(e: serde_json::Error) -> Self {
+        RpcError::Transport(e.to_string())
+    }
+}
+
+const DEFAULT_RPC_TIMEOUT_SECS: u64 = 10;
+
 #[derive(Debug)]
 pub struct RabbitMQConnection {
     pub connection: Connection,
     pub channel: Channel,
+    rpc_timeout: Duration,
 }
 
 impl RabbitMQConnection {
@@ -34,9 +189,14 @@ impl RabbitMQConnection {
                 e
             })?;
 
+        let rpc_timeout = std::env::var("RPC_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RPC_TIMEOUT_SECS);
         let rabbit = Self {
             connection,
             channel,
+            rpc_timeout: Duration::from_secs(rpc_timeout),
         };
 
         info!("Setting up queues and exchanges");
@@ -75,6 +235,34 @@ impl RabbitMQConnection {
                 e
             })?;
 
+        info!("Declaring exchange: worker_events (fanout)");
+        self.channel
+            .exchange_declare(
+                "worker_events",
+                ExchangeKind::Fanout,
+                ExchangeDeclareOptions::default(),
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to declare worker_events exchange: {}", e);
+                e
+            })?;
+
+        info!("Declaring exchange: worker_control (fanout)");
+        self.channel
+            .exchange_declare(
+                "worker_control",
+                ExchangeKind::Fanout,
+                ExchangeDeclareOptions::default(),
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to declare worker_control exchange: {}", e);
+                e
+            })?;
+
         info!("Declaring exchange: game_scores (fanout)");
         self.channel
             .exchange_declare(
@@ -115,6 +303,47 @@ impl RabbitMQConnection {
                 e
             })?;
 
+        info!("Declaring queue: rpc_dead_letters");
+        self.channel
+            .queue_declare(
+                "rpc_dead_letters",
+                QueueDeclareOptions::default(),
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to declare rpc_dead_letters queue: {}", e);
+                e
+            })?;
+
+        info!("Declaring exchange: rpc_heartbeats (fanout)");
+        self.channel
+            .exchange_declare(
+                "rpc_heartbeats",
+                ExchangeKind::Fanout,
+                ExchangeDeclareOptions::default(),
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to declare rpc_heartbeats exchange: {}", e);
+                e
+            })?;
+
+        info!("Declaring exchange: rpc_broadcast (fanout)");
+        self.channel
+            .exchange_declare(
+                "rpc_broadcast",
+                ExchangeKind::Fanout,
+                ExchangeDeclareOptions::default(),
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to declare rpc_broadcast exchange: {}", e);
+                e
+            })?;
+
         info!("Declaring exchange: drawing_fanout (fanout)");
         self.channel
             .exchange_declare(
@@ -154,13 +383,17 @@ impl RabbitMQConnection {
         message: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         debug!("Publishing message to queue: {}", queue);
+        // A unique message_id lets consumers recognise redeliveries of the same message
+        let properties = BasicProperties::default()
+            .with_message_id(Uuid::new_v4().to_string().into())
+            .with_timestamp(chrono::Utc::now().timestamp() as u64);
         self.channel
             .basic_publish(
                 "",
                 queue,
                 BasicPublishOptions::default(),
                 message.as_bytes(),
-                BasicProperties::default(),
+                properties,
             )
             .await
             .map_err(|e| {
@@ -234,14 +467,28 @@ impl RabbitMQConnection {
         Ok(())
     }
 
+    /// Consumes `queue` on a dedicated channel limited to `prefetch` unacked deliveries,
+    /// so the consumer can be cancelled and its channel closed independently.
     #[instrument(skip(self), fields(queue = %queue))]
-    pub async fn consume_queue(
+    pub async fn consume_queue_with_prefetch(
         &self,
         queue: &str,
-    ) -> Result<Consumer, Box<dyn std::error::Error + Send + Sync>> {
-        info!("Starting consumer for queue: {}", queue);
-        let consumer = self
-            .channel
+        prefetch: u16,
+    ) -> Result<(Channel, Consumer), Box<dyn std::error::Error + Send + Sync>> {
+        info!("Starting consumer with prefetch {} for queue: {}", prefetch, queue);
+        let channel = self.connection.create_channel().await
+            .map_err(|e| {
+                error!("Failed to create consumer channel for queue {}: {}", queue, e);
+                e
+            })?;
+        channel
+            .basic_qos(prefetch, BasicQosOptions::default())
+            .await
+            .map_err(|e| {
+                error!("Failed to set prefetch for queue {}: {}", queue, e);
+                e
+            })?;
+        let consumer = channel
             .basic_consume(
                 queue,
                 "",
@@ -253,15 +500,177 @@ impl RabbitMQConnection {
                 error!("Failed to create consumer for queue {}: {}", queue, e);
                 e
             })?;
-        info!("Consumer created successfully for queue: {}", queue);
+        info!("Consumer {} created successfully for queue: {}", consumer.tag(), queue);
+        Ok((channel, consumer))
+    }
+
+    #[instrument(skip(self), fields(exchange = %exchange))]
+    pub async fn subscribe_to_exchange(
+        &self,
+        exchange: &str,
+    ) -> Result<Consumer, Box<dyn std::error::Error + Send + Sync>> {
+        let (_, consumer) = declare_subscriber(&self.channel, exchange).await?;
         Ok(consumer)
     }
 
-    #[instrument(skip(self, request_data), fields(correlation_id))]
-    pub async fn rpc_call(
+    /// Subscribes on a channel of its own, so closing that channel removes the
+    /// subscriber's queue without touching anyone else's. Returns the channel, the
+    /// server-named queue and its consumer.
+    pub async fn subscribe_on_new_channel(
+        &self,
+        exchange: &str,
+    ) -> Result<(Channel, String, Consumer), Box<dyn std::error::Error + Send + Sync>> {
+        let channel = self.connection.create_channel().await
+            .map_err(|e| {
+                error!("Failed to create subscriber channel for exchange {}: {}", exchange, e);
+                e
+            })?;
+        let (queue, consumer) = declare_subscriber(&channel, exchange).await?;
+        Ok((channel, queue, consumer))
+    }
+
+    /// Reads live message and consumer counts with passive declares. A passive declare of a
+    /// missing queue closes its channel, so this works on a channel of its own and opens a
+    /// new one after each failure.
+    #[instrument(skip(self, queues))]
+    pub async fn inspect_queues(&self, queues: &[String]) -> Vec<(String, Result<QueueDepth, String>)> {
+        let mut channel: Option<Channel> = None;
+        let mut depths = Vec::with_capacity(queues.len());
+        for queue in queues {
+            if channel.as_ref().is_none_or(|c| !c.status().connected()) {
+                match self.connection.create_channel().await {
+                    Ok(new_channel) => channel = Some(new_channel),
+                    Err(e) => {
+                        error!("Failed to create channel for queue inspection: {}", e);
+                        depths.push((queue.clone(), Err(e.to_string())));
+                        continue;
+                    }
+                }
+            }
+            let Some(inspector) = channel.as_ref() else { continue };
+            let depth = inspector
+                .queue_declare(
+                    queue,
+                    QueueDeclareOptions {
+                        passive: true,
+                        ..Default::default()
+                    },
+                    FieldTable::default(),
+                )
+                .await
+                .map(|q| QueueDepth {
+                    message_count: q.message_count(),
+                    consumer_count: q.consumer_count(),
+                })
+                .map_err(|e| {
+                    debug!("Passive declare of queue {} failed: {}", queue, e);
+                    e.to_string()
+                });
+            depths.push((queue.clone(), depth));
+        }
+        close_inspector(channel).await;
+        depths
+    }
+
+    /// Checks which exchanges exist with passive declares, on its own channel for the same
+    /// reason as `inspect_queues`.
+    #[instrument(skip(self, exchanges))]
+    pub async fn inspect_exchanges(&self, exchanges: &[(&str, &str)]) -> Vec<(String, Result<(), String>)> {
+        let mut channel: Option<Channel> = None;
+        let mut found = Vec::with_capacity(exchanges.len());
+        for (exchange, kind) in exchanges {
+            if channel.as_ref().is_none_or(|c| !c.status().connected()) {
+                match self.connection.create_channel().await {
+                    Ok(new_channel) => channel = Some(new_channel),
+                    Err(e) => {
+                        error!("Failed to create channel for exchange inspection: {}", e);
+                        found.push((exchange.to_string(), Err(e.to_string())));
+                        continue;
+                    }
+                }
+            }
+            let Some(inspector) = channel.as_ref() else { continue };
+            let kind = match *kind {
+                "direct" => ExchangeKind::Direct,
+                "topic" => ExchangeKind::Topic,
+                "headers" => ExchangeKind::Headers,
+                _ => ExchangeKind::Fanout,
+            };
+            let exists = inspector
+                .exchange_declare(
+                    exchange,
+                    kind,
+                    ExchangeDeclareOptions {
+                        passive: true,
+                        ..Default::default()
+                    },
+                    FieldTable::default(),
+                )
+                .await
+                .map_err(|e| {
+                    debug!("Passive declare of exchange {} failed: {}", exchange, e);
+                    e.to_string()
+                });
+            found.push((exchange.to_string(), exists));
+        }
+        close_inspector(channel).await;
+        found
+    }
+
+    /// Sends `request_data` to `rpc_requests` and waits up to `RPC_TIMEOUT_SECS` (default
+    /// 10) for the reply. Error envelopes from the worker come back as `RpcError::Remote`.
+    pub async fn rpc_call(&self, request_data: Value) -> Result<RpcReply, RpcError> {
+        let reply = self.request_reply(request_data, None, None).await?;
+        check_reply(reply)
+    }
+
+    /// Like `rpc_call`, but passes the progress parts of a streaming reply to `parts` as
+    /// they arrive. The timeout restarts with every part.
+    pub async fn rpc_call_streaming(
+        &self,
+        request_data: Value,
+        parts: mpsc::UnboundedSender<RpcPart>,
+    ) -> Result<RpcReply, RpcError> {
+        let reply = self.request_reply(request_data, None, Some(&parts)).await?;
+        check_reply(reply)
+    }
+
+    /// Sends a JSON-RPC 2.0 request or batch to `rpc_requests` and returns the worker's
+    /// JSON-RPC reply as is; error objects stay in the payload rather than becoming an
+    /// `RpcError`.
+    pub async fn jsonrpc_call(&self, body: Value) -> Result<RpcReply, RpcError> {
+        self.request_reply(body, Some(JSONRPC_CONTENT_TYPE), None).await
+    }
+
+    /// Publishes JSON-RPC notifications to `rpc_requests` without a `reply_to`, since
+    /// nobody waits for an answer.
+    #[instrument(skip(self, body))]
+    pub async fn jsonrpc_notify(&self, body: &Value) -> Result<(), RpcError> {
+        let properties = BasicProperties::default().with_content_type(JSONRPC_CONTENT_TYPE.into());
+        self.channel
+            .basic_publish(
+                "",
+                "rpc_requests",
+                BasicPublishOptions::default(),
+                body.to_string().as_bytes(),
+                properties,
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to publish JSON-RPC notification: {}", e);
+                e
+            })?;
+        info!("Published JSON-RPC notification");
+        Ok(())
+    }
+
+    #[instrument(skip(self, request_data, parts), fields(correlation_id))]
+    async fn request_reply(
         &self,
         request_data: Value,
-    ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        content_type: Option<&str>,
+        parts: Option<&mpsc::UnboundedSender<RpcPart>>,
+    ) -> Result<RpcReply, RpcError> {
         let correlation_id = Uuid::new_v4().to_string();
         let reply_queue = format!("rpc_reply_{}", correlation_id);
         
@@ -285,17 +694,218 @@ impl RabbitMQConnection {
                 e
             })?;
 
-        let mut properties = BasicProperties::default();
-        properties = properties.with_correlation_id(correlation_id.clone().into());
-        properties = properties.with_reply_to(reply_queue.clone().into());
+        let result = self
+            .send_and_await_reply(&reply_queue, &correlation_id, &request_data, content_type, parts)
+            .await;
+
+        if let Err(e) = self
+            .channel
+            .queue_delete(&reply_queue, QueueDeleteOptions::default())
+            .await
+        {
+            warn!("Failed to delete reply queue {}: {}", reply_queue, e);
+        }
+
+        result
+    }
+
+    async fn send_and_await_reply(
+        &self,
+        reply_queue: &str,
+        correlation_id: &str,
+        request_data: &Value,
+        content_type: Option<&str>,
+        parts: Option<&mpsc::UnboundedSender<RpcPart>>,
+    ) -> Result<RpcReply, RpcError> {
+        let sent_at = std::time::Instant::now();
+        self.publish_request("", "rpc_requests", reply_queue, correlation_id, request_data, content_type)
+            .await?;
+        let mut consumer = self.consume_replies(reply_queue).await?;
+
+        use futures_util::StreamExt;
+        let mut received_parts = 0;
+        loop {
+            let delivery = match tokio::time::timeout(self.rpc_timeout, consumer.next()).await {
+                Ok(Some(Ok(delivery))) => delivery,
+                Ok(Some(Err(e))) => {
+                    error!("RPC delivery error for correlation_id {}: {}", correlation_id, e);
+                    return Err(e.into());
+                }
+                Ok(None) => {
+                    return Err(RpcError::Transport("Reply consumer closed before a reply arrived".to_string()));
+                }
+                Err(_) => {
+                    warn!("RPC timeout for correlation_id: {}", correlation_id);
+                    return Err(RpcError::Timeout {
+                        correlation_id: correlation_id.to_string(),
+                        after: self.rpc_timeout,
+                    });
+                }
+            };
+
+            let (seq, is_final) = reply_sequence(&delivery);
+            if is_final {
+                let mut reply = read_reply(delivery, correlation_id, sent_at).await?;
+                reply.parts = received_parts;
+                return Ok(reply);
+            }
+
+            let part = read_reply(delivery, correlation_id, sent_at).await?;
+            debug!("Received progress part {} for correlation_id: {}", seq, correlation_id);
+            received_parts += 1;
+            if let Some(parts) = parts {
+                let _ = parts.send(RpcPart {
+                    correlation_id: part.correlation_id,
+                    seq,
+                    payload: part.payload,
+                    worker_id: part.worker_id,
+                    elapsed_ms: part.latency_ms,
+                });
+            }
+        }
+    }
+
+    /// Publishes one request to every RPC worker through the `rpc_broadcast` fanout
+    /// exchange and gathers the replies sharing its correlation id, until `expected`
+    /// replies have arrived (if given) or `deadline` has passed.
+    #[instrument(skip(self, request_data), fields(correlation_id))]
+    pub async fn scatter_call(
+        &self,
+        request_data: Value,
+        expected: Option<usize>,
+        deadline: Duration,
+    ) -> Result<ScatterReplies, RpcError> {
+        let correlation_id = Uuid::new_v4().to_string();
+        let reply_queue = format!("rpc_scatter_{}", correlation_id);
+
+        tracing::Span::current().record("correlation_id", &correlation_id);
+        info!(
+            "Starting scatter call with correlation_id: {} (expecting {:?} replies within {:?})",
+            correlation_id, expected, deadline
+        );
+
+        debug!("Declaring temporary reply queue: {}", reply_queue);
+        self.channel
+            .queue_declare(
+                &reply_queue,
+                QueueDeclareOptions {
+                    exclusive: true,
+                    auto_delete: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .map_err(|e| {
+                error!("Failed to declare reply queue {}: {}", reply_queue, e);
+                e
+            })?;
+
+        let result = self
+            .gather_replies(&reply_queue, &correlation_id, &request_data, expected, deadline)
+            .await;
+
+        if let Err(e) = self
+            .channel
+            .queue_delete(&reply_queue, QueueDeleteOptions::default())
+            .await
+        {
+            warn!("Failed to delete reply queue {}: {}", reply_queue, e);
+        }
+
+        let gathered = result?;
+        info!(
+            "Scatter call {} gathered {} replies in {}ms{}",
+            correlation_id,
+            gathered.replies.len(),
+            gathered.elapsed_ms,
+            if gathered.timed_out { " before its deadline passed" } else { "" }
+        );
+        Ok(gathered)
+    }
+
+    async fn gather_replies(
+        &self,
+        reply_queue: &str,
+        correlation_id: &str,
+        request_data: &Value,
+        expected: Option<usize>,
+        deadline: Duration,
+    ) -> Result<ScatterReplies, RpcError> {
+        let sent_at = std::time::Instant::now();
+        let deadline_at = tokio::time::Instant::now() + deadline;
+        self.publish_request("rpc_broadcast", "", reply_queue, correlation_id, request_data, None)
+            .await?;
+        let mut consumer = self.consume_replies(reply_queue).await?;
+
+        use futures_util::StreamExt;
+        let mut replies = Vec::new();
+        let mut timed_out = false;
+        while expected.is_none_or(|expected| replies.len() < expected) {
+            let delivery = match tokio::time::timeout_at(deadline_at, consumer.next()).await {
+                Ok(Some(Ok(delivery))) => delivery,
+                Ok(Some(Err(e))) => {
+                    error!("Scatter delivery error for correlation_id {}: {}", correlation_id, e);
+                    return Err(e.into());
+                }
+                Ok(None) => {
+                    warn!("Scatter reply consumer for {} closed early", correlation_id);
+                    break;
+                }
+                Err(_) => {
+                    timed_out = expected.is_some();
+                    break;
+                }
+            };
+
+            let reply_id = delivery.properties.correlation_id().as_ref().map(|id| id.as_str().to_string());
+            if reply_id.as_deref() != Some(correlation_id) {
+                warn!("Discarding reply with correlation_id {:?} on scatter queue {}", reply_id, reply_queue);
+                let _ = delivery.ack(BasicAckOptions::default()).await;
+                continue;
+            }
+            if !reply_sequence(&delivery).1 {
+                // Only final replies count; scatter calls do not relay progress
+                let _ = delivery.ack(BasicAckOptions::default()).await;
+                continue;
+            }
+            match read_reply(delivery, correlation_id, sent_at).await {
+                Ok(reply) => replies.push(reply),
+                Err(e) => warn!("Skipping unreadable scatter reply for {}: {}", correlation_id, e),
+            }
+        }
+
+        Ok(ScatterReplies {
+            correlation_id: correlation_id.to_string(),
+            replies,
+            timed_out,
+            elapsed_ms: sent_at.elapsed().as_millis() as u64,
+        })
+    }
+
+    async fn publish_request(
+        &self,
+        exchange: &str,
+        routing_key: &str,
+        reply_queue: &str,
+        correlation_id: &str,
+        request_data: &Value,
+        content_type: Option<&str>,
+    ) -> Result<(), RpcError> {
+        let mut properties = BasicProperties::default()
+            .with_correlation_id(correlation_id.into())
+            .with_reply_to(reply_queue.into());
+        if let Some(content_type) = content_type {
+            properties = properties.with_content_type(content_type.into());
+        }
 
         debug!("Publishing RPC request with correlation_id: {}", correlation_id);
         self.channel
             .basic_publish(
-                "",
-                "rpc_requests",
+                exchange,
+                routing_key,
                 BasicPublishOptions::default(),
-                serde_json::to_string(&request_data)
+                serde_json::to_string(request_data)
                     .map_err(|e| {
                         error!("Failed to serialize RPC request: {}", e);
                         e
@@ -308,12 +918,15 @@ impl RabbitMQConnection {
                 error!("Failed to publish RPC request: {}", e);
                 e
             })?;
+        Ok(())
+    }
 
+    async fn consume_replies(&self, reply_queue: &str) -> Result<Consumer, RpcError> {
         debug!("Waiting for RPC response on queue: {}", reply_queue);
         let consumer = self
             .channel
             .basic_consume(
-                &reply_queue,
+                reply_queue,
                 "",
                 BasicConsumeOptions::default(),
                 FieldTable::default(),
@@ -323,50 +936,131 @@ impl RabbitMQConnection {
                 error!("Failed to create RPC reply consumer: {}", e);
                 e
             })?;
+        Ok(consumer)
+    }
+}
 
-        use futures_util::StreamExt;
-        let mut stream = consumer.into_stream();
-        if let Some(delivery_result) = stream.next().await {
-            match delivery_result {
-                Ok(delivery) => {
-                    debug!("Received RPC response for correlation_id: {}", correlation_id);
-                    let response: Value = serde_json::from_slice(&delivery.data)
-                        .map_err(|e| {
-                            error!("Failed to deserialize RPC response: {}", e);
-                            e
-                        })?;
-                    
-                    delivery.ack(BasicAckOptions::default()).await
-                        .map_err(|e| {
-                            warn!("Failed to ack RPC response: {}", e);
-                            e
-                        })?;
-
-                    self.channel
-                        .queue_delete(&reply_queue, QueueDeleteOptions::default())
-                        .await
-                        .map_err(|e| {
-                            warn!("Failed to delete reply queue {}: {}", reply_queue, e);
-                            e
-                        })?;
-
-                    info!("RPC call completed successfully for correlation_id: {}", correlation_id);
-                    return Ok(response);
-                }
-                Err(e) => {
-                    error!("RPC delivery error for correlation_id {}: {}", correlation_id, e);
-                    let _ = self.channel
-                        .queue_delete(&reply_queue, QueueDeleteOptions::default())
-                        .await;
-                    return Err(format!("RPC delivery error: {}", e).into());
-                }
-            }
+async fn close_inspector(channel: Option<Channel>) {
+    if let Some(channel) = channel.filter(|c| c.status().connected()) {
+        if let Err(e) = channel.close(200, "OK").await {
+            debug!("Failed to close inspection channel: {}", e);
         }
+    }
+}
 
-        warn!("RPC timeout for correlation_id: {}", correlation_id);
-        let _ = self.channel
-            .queue_delete(&reply_queue, QueueDeleteOptions::default())
-            .await;
-        Err("RPC timeout".into())
+/// Turns an error envelope from the worker into `RpcError::Remote`.
+fn check_reply(reply: RpcReply) -> Result<RpcReply, RpcError> {
+    if let Some(remote) = RemoteError::from_reply(&reply) {
+        warn!("RPC call {} failed remotely: {} ({})", reply.correlation_id, remote.message, remote.code);
+        return Err(RpcError::Remote(Box::new(remote)));
     }
+    info!("RPC call completed successfully for correlation_id: {}", reply.correlation_id);
+    Ok(reply)
+}
+
+/// Decodes and acks one reply delivery, timing it from `sent_at`.
+async fn read_reply(
+    delivery: Delivery,
+    correlation_id: &str,
+    sent_at: std::time::Instant,
+) -> Result<RpcReply, RpcError> {
+    let latency_ms = sent_at.elapsed().as_millis() as u64;
+    debug!("Received RPC response for correlation_id: {} after {}ms", correlation_id, latency_ms);
+    let worker_id = delivery
+        .properties
+        .app_id()
+        .as_ref()
+        .map(|id| id.as_str().to_string());
+    let response: Value = serde_json::from_slice(&delivery.data)
+        .map_err(|e| {
+            error!("Failed to deserialize RPC response: {}", e);
+            e
+        })?;
+
+    delivery.ack(BasicAckOptions::default()).await
+        .map_err(|e| {
+            warn!("Failed to ack RPC response: {}", e);
+            e
+        })?;
+
+    Ok(RpcReply {
+        payload: response,
+        correlation_id: correlation_id.to_string(),
+        latency_ms,
+        worker_id,
+        parts: 0,
+    })
+}
+
+/// The `x-seq` and `x-final` headers of a reply. Replies without them, e.g. from workers
+/// that predate streaming, are final.
+fn reply_sequence(delivery: &Delivery) -> (u32, bool) {
+    let Some(headers) = delivery.properties.headers() else {
+        return (0, true);
+    };
+    let headers = headers.inner();
+    let seq = match headers.get("x-seq") {
+        Some(AMQPValue::LongUInt(seq)) => *seq,
+        Some(AMQPValue::LongLongInt(seq)) => u32::try_from(*seq).unwrap_or_default(),
+        _ => 0,
+    };
+    let is_final = !matches!(headers.get("x-final"), Some(AMQPValue::Boolean(false)));
+    (seq, is_final)
+}
+
+/// Declares an exclusive, auto-delete, server-named queue on `channel`, binds it to
+/// `exchange` and starts consuming it without acks.
+async fn declare_subscriber(
+    channel: &Channel,
+    exchange: &str,
+) -> Result<(String, Consumer), Box<dyn std::error::Error + Send + Sync>> {
+    info!("Subscribing to exchange: {}", exchange);
+    let queue = channel
+        .queue_declare(
+            "",
+            QueueDeclareOptions {
+                exclusive: true,
+                auto_delete: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to declare subscriber queue for exchange {}: {}", exchange, e);
+            e
+        })?;
+
+    debug!("Binding queue {} to exchange {}", queue.name().as_str(), exchange);
+    channel
+        .queue_bind(
+            queue.name().as_str(),
+            exchange,
+            "",
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to bind subscriber queue to exchange {}: {}", exchange, e);
+            e
+        })?;
+
+    let consumer = channel
+        .basic_consume(
+            queue.name().as_str(),
+            "",
+            BasicConsumeOptions {
+                no_ack: true,
+                ..Default::default()
+            },
+            FieldTable::default(),
+        )
+        .await
+        .map_err(|e| {
+            error!("Failed to create subscriber consumer for exchange {}: {}", exchange, e);
+            e
+        })?;
+    info!("Subscribed to exchange {} via queue {}", exchange, queue.name().as_str());
+    Ok((queue.name().as_str().to_string(), consumer))
 }
@@ -1,7 +1,57 @@
+use std::collections::HashMap;
 use redis::{Client, RedisResult, AsyncCommands};
 use serde_json;
 use tracing::{info, error, warn, debug, instrument};
 
+const LOGGER_HISTORY_KEY: &str = "logger:history";
+const GAME_ROUNDS_KEY: &str = "game:rounds";
+const GAME_ROUNDS_MAX: isize = 500;
+const GAME_ROUND_SCORES_TTL_SECS: i64 = 7 * 24 * 3600;
+
+/// Counts a click unless the round already has a winner, and claims the win for the
+/// first player to reach the target. Running it as one script keeps the check and the
+/// claim atomic across every server instance sharing this Redis.
+/// KEYS: round scores, round winner, all-time clicks. ARGV: player, target, ttl.
+const GAME_CLICK_SCRIPT: &str = r#"
+local winner = redis.call('GET', KEYS[2])
+if winner then
+  return {2, 0, winner}
+end
+local score = tonumber(redis.call('ZINCRBY', KEYS[1], 1, ARGV[1]))
+redis.call('ZINCRBY', KEYS[3], 1, ARGV[1])
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+if score >= tonumber(ARGV[2]) then
+  redis.call('SET', KEYS[2], ARGV[1], 'EX', ARGV[3])
+  return {1, score, ARGV[1]}
+end
+return {0, score, ''}
+"#;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClickOutcome {
+    Counted { score: u32 },
+    Won { score: u32 },
+    RoundOver { winner: String },
+}
+pub const GAME_LEADERBOARD_CLICKS: &str = "clicks";
+pub const GAME_LEADERBOARD_WINS: &str = "wins";
+
+fn game_round_seq_key(room: &str) -> String {
+    format!("game:room:{}:round", room)
+}
+
+fn game_round_scores_key(room: &str, round: u32) -> String {
+    format!("game:room:{}:round:{}:scores", room, round)
+}
+
+fn game_round_winner_key(room: &str, round: u32) -> String {
+    format!("game:room:{}:round:{}:winner", room, round)
+}
+
+fn game_leaderboard_key(board: &str) -> String {
+    format!("game:leaderboard:{}", board)
+}
+
 #[derive(Debug)]
 pub struct RedisStore {
     client: Client,
@@ -20,6 +70,11 @@ impl RedisStore {
         Ok(RedisStore { client })
     }
 
+    /// A handle to the same server for components that keep their own connection.
+    pub fn client(&self) -> Client {
+        self.client.clone()
+    }
+
     #[instrument(skip(self))]
     pub async fn get_connection(&self) -> RedisResult<redis::aio::Connection> {
         debug!("Getting Redis connection");
@@ -172,4 +227,201 @@ impl RedisStore {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[instrument(skip(self, entry_json), fields(entry_len = entry_json.len()))]
+    pub async fn append_logger_history(&self, entry_json: &str) -> RedisResult<String> {
+        debug!("Appending logger message to history");
+        let mut conn = self.get_connection().await?;
+        redis::cmd("XADD")
+            .arg(LOGGER_HISTORY_KEY)
+            .arg("*")
+            .arg("entry")
+            .arg(entry_json)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("Failed to append logger history: {}", e);
+                e
+            })
+    }
+
+    /// Returns up to `count` history entries between the stream ids `start` and `end`
+    /// (inclusive unless prefixed with `(`), newest first, as `(id, entry_json)` pairs.
+    #[instrument(skip(self))]
+    pub async fn read_logger_history(
+        &self,
+        start: &str,
+        end: &str,
+        count: usize,
+    ) -> RedisResult<Vec<(String, String)>> {
+        debug!("Reading logger history");
+        let mut conn = self.get_connection().await?;
+        let entries: Vec<(String, Vec<String>)> = redis::cmd("XREVRANGE")
+            .arg(LOGGER_HISTORY_KEY)
+            .arg(end)
+            .arg(start)
+            .arg("COUNT")
+            .arg(count)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("Failed to read logger history: {}", e);
+                e
+            })?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(id, fields)| {
+                let entry = fields.chunks(2).find(|pair| pair[0] == "entry")?.get(1)?.clone();
+                Some((id, entry))
+            })
+            .collect())
+    }
+
+    /// Trims the logger history to roughly `max_entries`, returning how many were removed.
+    #[instrument(skip(self))]
+    pub async fn trim_logger_history_by_count(&self, max_entries: usize) -> RedisResult<u64> {
+        let mut conn = self.get_connection().await?;
+        redis::cmd("XTRIM")
+            .arg(LOGGER_HISTORY_KEY)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(max_entries)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("Failed to trim logger history by count: {}", e);
+                e
+            })
+    }
+
+    /// Drops logger history entries older than `oldest_ms` (epoch millis).
+    #[instrument(skip(self))]
+    pub async fn trim_logger_history_by_age(&self, oldest_ms: i64) -> RedisResult<u64> {
+        let mut conn = self.get_connection().await?;
+        redis::cmd("XTRIM")
+            .arg(LOGGER_HISTORY_KEY)
+            .arg("MINID")
+            .arg(oldest_ms)
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("Failed to trim logger history by age: {}", e);
+                e
+            })
+    }
+
+    /// Allocates the next round number for a room. Round numbers survive restarts so
+    /// score keys from earlier rounds are never reused.
+    #[instrument(skip(self))]
+    pub async fn next_game_round(&self, room: &str) -> RedisResult<u32> {
+        let mut conn = self.get_connection().await?;
+        conn.incr(game_round_seq_key(room), 1)
+            .await
+            .map_err(|e| {
+                error!("Failed to allocate round for room {}: {}", room, e);
+                e
+            })
+    }
+
+    #[instrument(skip(self))]
+    pub async fn current_game_round(&self, room: &str) -> RedisResult<Option<u32>> {
+        let mut conn = self.get_connection().await?;
+        conn.get(game_round_seq_key(room))
+            .await
+            .map_err(|e| {
+                error!("Failed to read current round for room {}: {}", room, e);
+                e
+            })
+    }
+
+    /// Counts a click in the round's sorted set and the all-time clicks leaderboard.
+    /// Exactly one click per round comes back as `Won`; once a round is won, further
+    /// clicks are not counted and come back as `RoundOver`.
+    #[instrument(skip(self))]
+    pub async fn record_game_click(
+        &self,
+        room: &str,
+        round: u32,
+        player: &str,
+        winning_score: u32,
+    ) -> RedisResult<ClickOutcome> {
+        let mut conn = self.get_connection().await?;
+        let (status, score, winner): (i64, i64, String) = redis::Script::new(GAME_CLICK_SCRIPT)
+            .key(game_round_scores_key(room, round))
+            .key(game_round_winner_key(room, round))
+            .key(game_leaderboard_key(GAME_LEADERBOARD_CLICKS))
+            .arg(player)
+            .arg(winning_score)
+            .arg(GAME_ROUND_SCORES_TTL_SECS)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("Failed to record click for {} in room {}: {}", player, room, e);
+                e
+            })?;
+        let score = score.max(0) as u32;
+        Ok(match status {
+            1 => ClickOutcome::Won { score },
+            2 => ClickOutcome::RoundOver { winner },
+            _ => ClickOutcome::Counted { score },
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_round_scores(&self, room: &str, round: u32) -> RedisResult<HashMap<String, u32>> {
+        let mut conn = self.get_connection().await?;
+        let scores: Vec<(String, f64)> = conn
+            .zrange_withscores(game_round_scores_key(room, round), 0, -1)
+            .await
+            .map_err(|e| {
+                error!("Failed to read scores for room {} round {}: {}", room, round, e);
+                e
+            })?;
+        Ok(scores.into_iter().map(|(player, score)| (player, score as u32)).collect())
+    }
+
+    /// Stores a finished round's result and credits the winner on the all-time wins board.
+    #[instrument(skip(self, result_json), fields(result_len = result_json.len()))]
+    pub async fn record_round_result(&self, winner: Option<&str>, result_json: &str) -> RedisResult<()> {
+        let mut conn = self.get_connection().await?;
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .lpush(GAME_ROUNDS_KEY, result_json).ignore()
+            .ltrim(GAME_ROUNDS_KEY, 0, GAME_ROUNDS_MAX - 1).ignore();
+        if let Some(winner) = winner {
+            pipe.zincr(game_leaderboard_key(GAME_LEADERBOARD_WINS), winner, 1).ignore();
+        }
+        pipe.query_async(&mut conn)
+            .await
+            .map_err(|e| {
+                error!("Failed to record round result: {}", e);
+                e
+            })
+    }
+
+    /// Returns the top `limit` players of an all-time leaderboard, highest first.
+    #[instrument(skip(self))]
+    pub async fn get_game_leaderboard(&self, board: &str, limit: usize) -> RedisResult<Vec<(String, u64)>> {
+        let mut conn = self.get_connection().await?;
+        let entries: Vec<(String, f64)> = conn
+            .zrevrange_withscores(game_leaderboard_key(board), 0, limit as isize - 1)
+            .await
+            .map_err(|e| {
+                error!("Failed to read {} leaderboard: {}", board, e);
+                e
+            })?;
+        Ok(entries.into_iter().map(|(player, score)| (player, score as u64)).collect())
+    }
+
+    /// Returns the most recent round results as JSON strings, newest first.
+    #[instrument(skip(self))]
+    pub async fn get_round_results(&self) -> RedisResult<Vec<String>> {
+        let mut conn = self.get_connection().await?;
+        conn.lrange(GAME_ROUNDS_KEY, 0, GAME_ROUNDS_MAX - 1)
+            .await
+            .map_err(|e| {
+                error!("Failed to read round results: {}", e);
+                e
+            })
+    }
+}